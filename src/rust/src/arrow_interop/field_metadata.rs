@@ -0,0 +1,264 @@
+//! The custom metadata and extension type of arrow fields, kept alongside polars columns.
+//!
+//! A polars column has no place for the metadata of an arrow field and extension types are not
+//! polars data types, so both are lost when arrow data is imported. Instead, columns are imported
+//! with the storage type of their extension type and the field metadata is returned to R, where it
+//! is kept in the `field_metadata` attribute of the Series or DataFrame. The attribute of a Series
+//! is a list of `metadata`, `extension_name` and `extension_metadata`, the attribute of a
+//! DataFrame is a list of those named by column. The R methods pass the attribute on to the
+//! columns of their results that have the same name and dtype, and it is used again when the
+//! columns are exported or written to IPC and Parquet files.
+
+use crate::robj_to;
+use crate::rpolarserr::{rerr, RResult, WithRctx};
+use extendr_api::prelude::*;
+use polars::prelude::{self as pl, ArrowDataType, ArrowField, ArrowSchema, CompatLevel};
+use polars_core::prelude::{ArrayRef, PlSmallStr, PolarsResult, Series};
+use polars_core::utils::arrow::datatypes::{ExtensionType, Metadata};
+use polars_core::utils::arrow::ffi;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The name of the R attribute holding the field metadata of a Series or DataFrame.
+pub const ATTR_NAME: &str = "field_metadata";
+
+/// The field metadata of the columns of a DataFrame, by column name.
+pub type ColumnsMetadata = HashMap<PlSmallStr, FieldMetadata>;
+
+// keys of the field metadata that polars sets itself to restore its own data types
+const POLARS_METADATA_KEYS: [&str; 3] = ["_PL_ENUM_VALUES", "_PL_CATEGORICAL", "pl"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldMetadata {
+    pub metadata: Metadata,
+    /// The name and the serialized metadata of the extension type.
+    pub extension: Option<(PlSmallStr, Option<PlSmallStr>)>,
+}
+
+impl FieldMetadata {
+    pub fn from_arrow_field(field: &ArrowField) -> Self {
+        let metadata = field
+            .metadata
+            .as_deref()
+            .map(|md| {
+                md.iter()
+                    .filter(|(k, _)| !POLARS_METADATA_KEYS.contains(&k.as_str()))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let extension = match &field.dtype {
+            ArrowDataType::Extension(ext) => Some((ext.name.clone(), ext.metadata.clone())),
+            _ => None,
+        };
+        FieldMetadata {
+            metadata,
+            extension,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty() && self.extension.is_none()
+    }
+
+    pub fn metadata_robj(&self) -> Robj {
+        let mut values: Robj = self
+            .metadata
+            .values()
+            .map(|value| value.as_str())
+            .collect_robj();
+        values
+            .set_names(self.metadata.keys().map(|key| key.as_str()))
+            .unwrap();
+        values
+    }
+
+    pub fn extension_name_robj(&self) -> Robj {
+        match &self.extension {
+            Some((name, _)) => name.as_str().into(),
+            None => NULL.into(),
+        }
+    }
+
+    pub fn extension_metadata_robj(&self) -> Robj {
+        match &self.extension {
+            Some((_, Some(metadata))) => metadata.as_str().into(),
+            _ => NULL.into(),
+        }
+    }
+
+    pub fn set_metadata(&mut self, metadata: Robj) -> RResult<()> {
+        if metadata.is_null() || metadata.len() == 0 {
+            self.metadata.clear();
+            return Ok(());
+        }
+        let (Some(values), Some(keys)) = (metadata.as_str_iter(), metadata.names()) else {
+            return rerr()
+                .plain("must be a named character vector")
+                .bad_robj(&metadata)
+                .bad_arg("metadata");
+        };
+        self.metadata = keys
+            .zip(values)
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        Ok(())
+    }
+
+    pub fn set_extension(&mut self, name: Robj, metadata: Robj) -> RResult<()> {
+        let name = robj_to!(Option, String, name)?;
+        let metadata = robj_to!(Option, String, metadata)?;
+        self.extension = match (name, metadata) {
+            (Some(name), metadata) => Some((name.into(), metadata.map(Into::into))),
+            (None, None) => None,
+            (None, Some(_)) => {
+                return rerr()
+                    .plain("cannot be set on a field without an extension type")
+                    .bad_arg("extension_metadata")
+            }
+        };
+        Ok(())
+    }
+
+    /// The R value of the `field_metadata` attribute of a Series, `NULL` if `self` is empty.
+    pub fn to_robj(&self) -> Robj {
+        if self.is_empty() {
+            return NULL.into();
+        }
+        list!(
+            metadata = self.metadata_robj(),
+            extension_name = self.extension_name_robj(),
+            extension_metadata = self.extension_metadata_robj()
+        )
+        .into()
+    }
+
+    /// Read the `field_metadata` attribute of a Series, which may be `NULL`.
+    pub fn from_robj(robj: Robj) -> RResult<Self> {
+        let mut md = FieldMetadata::default();
+        if robj.is_null() {
+            return Ok(md);
+        }
+        let Some(list) = robj.as_list() else {
+            return rerr()
+                .plain("the field metadata must be a list")
+                .bad_robj(&robj);
+        };
+        let hm = list.into_hashmap();
+        let get = |name: &str| hm.get(name).cloned().unwrap_or_else(|| NULL.into());
+        md.set_metadata(get("metadata"))?;
+        md.set_extension(get("extension_name"), get("extension_metadata"))?;
+        Ok(md)
+    }
+
+    /// Add the metadata and extension type to a field exported by polars.
+    pub fn apply_to(&self, mut field: ArrowField) -> ArrowField {
+        if !self.metadata.is_empty() {
+            let mut metadata = self.metadata.clone();
+            metadata.extend(field.metadata.as_deref().cloned().unwrap_or_default());
+            field.metadata = Some(Arc::new(metadata));
+        }
+        if let Some((name, metadata)) = &self.extension {
+            field.dtype = ArrowDataType::Extension(Box::new(ExtensionType {
+                name: name.clone(),
+                inner: field.dtype,
+                metadata: metadata.clone(),
+            }));
+        }
+        field
+    }
+}
+
+/// Read the `field_metadata` attribute of a DataFrame, which may be `NULL`.
+pub fn columns_metadata_from_robj(robj: Robj) -> RResult<ColumnsMetadata> {
+    if robj.is_null() {
+        return Ok(ColumnsMetadata::new());
+    }
+    let Some(list) = robj.as_list() else {
+        return rerr()
+            .plain("the field metadata of the columns must be a named list")
+            .bad_robj(&robj);
+    };
+    list.iter()
+        .map(|(name, md)| Ok((name.into(), FieldMetadata::from_robj(md).bad_arg(name)?)))
+        .collect()
+}
+
+/// The R value of the `field_metadata` attribute of a DataFrame with the columns `fields`, only
+/// the columns with field metadata are listed. `NULL` if there are none.
+pub fn fields_metadata_robj(fields: &[ArrowField]) -> Robj {
+    let (names, values): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .map(|field| (field.name.as_str(), FieldMetadata::from_arrow_field(field)))
+        .filter(|(_, md)| !md.is_empty())
+        .map(|(name, md)| (name, md.to_robj()))
+        .unzip();
+    if names.is_empty() {
+        return NULL.into();
+    }
+    List::from_names_and_values(names, values).unwrap().into()
+}
+
+/// Set the `field_metadata` attribute of `robj`, a Series or DataFrame returned to R, unless `md`
+/// is `NULL`.
+pub fn set_attr(mut robj: Robj, md: Robj) -> RResult<Robj> {
+    if !md.is_null() {
+        robj.set_attrib(ATTR_NAME, md)?;
+    }
+    Ok(robj)
+}
+
+/// The arrow field of `s` with the metadata and extension type of `md`.
+pub fn series_arrow_field(s: &Series, md: &FieldMetadata, compat_level: CompatLevel) -> ArrowField {
+    md.apply_to(s.field().to_arrow(compat_level))
+}
+
+/// The arrow schema of `df` with the metadata and extension types of its columns in `md`.
+pub fn df_arrow_schema(
+    df: &pl::DataFrame,
+    md: &ColumnsMetadata,
+    compat_level: CompatLevel,
+) -> ArrowSchema {
+    let empty = FieldMetadata::default();
+    df.get_columns()
+        .iter()
+        .map(|c| {
+            let s = c.as_materialized_series();
+            let field = series_arrow_field(s, md.get(s.name()).unwrap_or(&empty), compat_level);
+            (field.name.clone(), field)
+        })
+        .collect()
+}
+
+// an extension array with the data type of its storage, the buffers are shared through the C
+// data interface which does not check the data type, unlike the constructors of the arrays
+fn to_storage_array(array: ArrayRef, storage: &ArrowDataType) -> PolarsResult<ArrayRef> {
+    let array = ffi::export_array_to_c(array);
+    unsafe { ffi::import_array_from_c(array, storage.clone()) }
+}
+
+/// Convert arrow arrays to a Series, like `Series::try_from((field, chunks))`. The extension type
+/// of `field` is replaced by its storage type, its metadata is read by
+/// `FieldMetadata::from_arrow_field()`.
+pub fn series_from_arrow(field: &ArrowField, chunks: Vec<ArrayRef>) -> PolarsResult<Series> {
+    match &field.dtype {
+        ArrowDataType::Extension(ext) => {
+            let storage = ArrowField::new(field.name.clone(), ext.inner.clone(), true)
+                .with_metadata(field.metadata.as_deref().cloned().unwrap_or_default());
+            let chunks = chunks
+                .into_iter()
+                .map(|array| to_storage_array(array, &ext.inner))
+                .collect::<PolarsResult<Vec<_>>>()?;
+            Series::try_from((&storage, chunks))
+        }
+        _ => Series::try_from((field, chunks)),
+    }
+}
+
+/// The polars data type of an arrow field, using the storage type of extension types.
+pub fn dtype_from_arrow_field(field: &ArrowField) -> pl::DataType {
+    match &field.dtype {
+        ArrowDataType::Extension(ext) => pl::DataType::from_arrow(&ext.inner, true, None),
+        _ => pl::DataType::from_arrow_field(field),
+    }
+}
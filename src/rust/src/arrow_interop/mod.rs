@@ -1,5 +1,9 @@
+pub mod field_metadata;
 pub mod to_rust;
 
+use crate::robj_to;
+use crate::rpolarserr::*;
+use polars_core::prelude::{ArrayRef, ArrowField, PolarsResult};
 use polars_core::utils::arrow;
 
 use extendr_api::prelude::*;
@@ -64,6 +68,60 @@ impl RPackage for NanoArrowRPackage {
     }
 }
 
+/// The address of an Arrow C struct, from an external pointer to it such as a `nanoarrow_array`
+/// or a `nanoarrow_schema`, or from its address formatted as a string.
+pub fn robj_to_ptr_addr(robj: Robj) -> RResult<usize> {
+    if robj.rtype() != Rtype::ExternalPtr {
+        return robj_to!(usize, robj);
+    }
+    let addr = unsafe { robj.external_ptr_addr::<std::ffi::c_void>() } as usize;
+    if addr == 0 {
+        return rerr().plain("the external pointer is null");
+    }
+    Ok(addr)
+}
+
+/// Export `array` to the `ArrowArray` and `ArrowSchema` at the given addresses. Structs that are
+/// not released yet are released before they are overwritten.
+///
+/// # Safety
+/// The addresses must point to valid, writable C structs.
+pub unsafe fn export_array_to_c(
+    array: ArrayRef,
+    field: &ArrowField,
+    array_addr: usize,
+    schema_addr: usize,
+) {
+    let mut schema = arrow::ffi::export_field_to_c(field);
+    let mut array = arrow::ffi::export_array_to_c(array);
+    unsafe {
+        std::ptr::swap_nonoverlapping(schema_addr as *mut arrow::ffi::ArrowSchema, &mut schema, 1);
+        std::ptr::swap_nonoverlapping(array_addr as *mut arrow::ffi::ArrowArray, &mut array, 1);
+    }
+}
+
+/// Import the `ArrowArray` and `ArrowSchema` at the given addresses. The array is moved out, so
+/// the caller's struct is left released, and the schema is only read.
+///
+/// # Safety
+/// The addresses must point to a valid `ArrowArray` and a valid `ArrowSchema` describing it.
+pub unsafe fn import_array_from_c(
+    array_addr: usize,
+    schema_addr: usize,
+) -> PolarsResult<(ArrowField, ArrayRef)> {
+    let field = unsafe {
+        arrow::ffi::import_field_from_c(&*(schema_addr as *const arrow::ffi::ArrowSchema))
+    }?;
+    let array = unsafe {
+        std::ptr::replace(
+            array_addr as *mut arrow::ffi::ArrowArray,
+            arrow::ffi::ArrowArray::empty(),
+        )
+    };
+    let array = unsafe { arrow::ffi::import_array_from_c(array, field.dtype.clone()) }?;
+    Ok((field, array))
+}
+
 #[extendr]
 pub fn polars_allocate_array_stream() -> Robj {
     let aas = Box::new(arrow::ffi::ArrowArrayStream::empty());
@@ -1,14 +1,13 @@
+use super::field_metadata::{dtype_from_arrow_field, fields_metadata_robj, series_from_arrow};
 use super::RArrowArrayClass;
 use extendr_api::prelude::*;
 use polars::prelude as pl;
-use polars_core::export::rayon::prelude::*;
 use polars_core::prelude::*;
-use polars_core::utils::accumulate_dataframes_vertical_unchecked;
+use polars_core::utils::arrow::array::StructArray;
 use polars_core::utils::arrow::ffi;
-use polars_core::POOL;
 use std::result::Result;
 
-pub fn arrow_array_to_rust(arrow_array: Robj) -> Result<ArrayRef, String> {
+pub fn arrow_array_to_rust(arrow_array: Robj) -> Result<(ArrowField, ArrayRef), String> {
     let mut array = Box::new(ffi::ArrowArray::empty());
     let mut schema = Box::new(ffi::ArrowSchema::empty());
     let (ext_a, ext_s) = unsafe {
@@ -23,91 +22,102 @@ pub fn arrow_array_to_rust(arrow_array: Robj) -> Result<ArrayRef, String> {
         .get_export_array_func()?
         .call(pairlist!(&arrow_array, ext_a, ext_s))?;
 
-    let array = unsafe {
+    unsafe {
         let field = ffi::import_field_from_c(schema.as_ref()).map_err(|err| err.to_string())?;
-        ffi::import_array_from_c(*array, field.dtype).map_err(|err| err.to_string())?
-    };
-    Ok(array)
+        let array =
+            ffi::import_array_from_c(*array, field.dtype.clone()).map_err(|err| err.to_string())?;
+        Ok((field, array))
+    }
 }
 
 unsafe fn wrap_make_external_ptr<T>(t: &mut T) -> Robj {
     unsafe { <Integers>::make_external_ptr(t, r!(extendr_api::NULL)) }
 }
 
-pub unsafe fn to_rust_df(rb: Robj) -> Result<pl::DataFrame, String> {
-    let rb = rb.as_list().ok_or("arrow record batches is not a List")?;
-    let get_columns_f = R!(r"\(x) x$columns")?.as_function().unwrap();
-
-    //read columns names of first batch, if not any batches return empty DataFrame
-    let robj_record_batch_names = if let Ok(first_rb) = rb.elt(0) {
-        call!(r"\(x) x$schema$names", first_rb).map_err(|err| format!("internal error: {}", err))?
-    } else {
-        return Ok(pl::DataFrame::default());
-    };
-    let names = robj_record_batch_names
-        .as_str_vector()
-        .ok_or_else(|| "internal error: Robj$schema$names is not a char vec".to_string())?
-        .into_iter()
-        .map(PlSmallStr::from_str)
-        .collect::<Vec<_>>();
-
-    //iterate over record batches
-    let rb_len = rb.len();
-    let dfs_iter = rb.iter().map(|(_, rb)| {
-        //do not run parallel unless data.type matches ...
-        let mut run_parallel = false;
+pub type ArrayStreamReader = ffi::ArrowArrayStreamReader<Box<ffi::ArrowArrayStream>>;
 
-        //get list of columns for this record batch
-        let columns_list = get_columns_f
-            .call(pairlist!(rb))?
-            .as_list()
-            .expect("arrow columns always wrapped in list");
-        let n_columns = columns_list.len();
+/// Take ownership of the stream allocated by `polars_allocate_array_stream()` and filled by the
+/// producer. The stream is released when the returned reader is dropped.
+///
+/// # Safety
+/// `stream_ptr_addr` must be the address of a stream from `polars_allocate_array_stream()` that
+/// is not imported anywhere else.
+pub unsafe fn import_array_stream(stream_ptr_addr: usize) -> PolarsResult<ArrayStreamReader> {
+    let stream = unsafe { Box::from_raw(stream_ptr_addr as *mut ffi::ArrowArrayStream) };
+    unsafe { ffi::ArrowArrayStreamReader::try_new(stream) }
+}
 
-        //collect vector of exported arrow arrays, one for each column
-        let array_iter = columns_list.into_iter().map(|(_, column)| {
-            let arr = arrow_array_to_rust(column)?;
-            run_parallel |= matches!(
-                arr.dtype(),
-                ArrowDataType::Utf8 | ArrowDataType::Dictionary(_, _, _)
-            );
-            let list_res: Result<_, String> = Ok(arr);
-            list_res
-        });
-        let arrays_vec = crate::utils::collect_hinted_result(n_columns, array_iter)?;
+/// The fields of a stream of record batches, i.e. of struct arrays.
+pub fn array_stream_fields(stream: &ArrayStreamReader) -> PolarsResult<Vec<ArrowField>> {
+    match stream.field().dtype().to_logical_type() {
+        ArrowDataType::Struct(fields) => Ok(fields.clone()),
+        dtype => pl::polars_bail!(
+            ComputeError: "only a stream of struct arrays can be imported as a DataFrame, got {:?}",
+            dtype
+        ),
+    }
+}
 
-        // we parallelize this part because we can have dtypes that are not zero copy
-        // for instance utf8 -> large-utf8
-        // dict encoded to categorical
+/// The fields of a struct array, i.e. of a record batch, as the columns of a DataFrame. Like for
+/// a record batch, the validity of the struct array itself is not used. The `field_metadata`
+/// attribute of the DataFrame is returned with it.
+pub fn struct_array_to_df(field: &ArrowField, array: ArrayRef) -> PolarsResult<(DataFrame, Robj)> {
+    let ArrowDataType::Struct(fields) = field.dtype.to_logical_type() else {
+        pl::polars_bail!(
+            ComputeError: "only a struct array can be imported as a DataFrame, got {:?}",
+            field.dtype
+        );
+    };
+    let array = array.as_any().downcast_ref::<StructArray>().unwrap();
+    let columns = fields
+        .iter()
+        .zip(array.values())
+        .map(|(field, values)| Ok(series_from_arrow(field, vec![values.clone()])?.into_column()))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    Ok((DataFrame::new(columns)?, fields_metadata_robj(fields)))
+}
 
-        let columns = if run_parallel {
-            POOL.install(|| {
-                arrays_vec
-                    .into_par_iter()
-                    .zip(names.par_iter())
-                    .map(|(arr, name)| {
-                        let s = Series::try_from((name.clone(), arr))
-                            .map_err(|err| err.to_string())?
-                            .into_column();
-                        Ok(s)
-                    })
-                    .collect::<Result<Vec<_>, String>>()
-            })
-        } else {
-            let iter = arrays_vec.into_iter().zip(names.iter()).map(|(arr, name)| {
-                let s = Series::try_from((name.clone(), arr))
-                    .map_err(|err| err.to_string())?
-                    .into_column();
-                Ok(s)
-            });
-            crate::utils::collect_hinted_result(n_columns, iter)
-        }?;
+/// Pull the record batches of `stream` into a DataFrame with the fields at `projection`, until
+/// `n_rows` rows are read. The arrow buffers are reused where the memory layout of polars
+/// matches.
+pub fn array_stream_to_df(
+    stream: &mut ArrayStreamReader,
+    fields: &[ArrowField],
+    projection: &[usize],
+    n_rows: Option<usize>,
+) -> PolarsResult<DataFrame> {
+    let mut chunks: Vec<Vec<ArrayRef>> = vec![Vec::new(); projection.len()];
+    let mut height = 0;
+    while n_rows.map_or(true, |n_rows| height < n_rows) {
+        let Some(array) = (unsafe { stream.next() }) else {
+            break;
+        };
+        let array = array?;
+        let Some(batch) = array.as_any().downcast_ref::<StructArray>() else {
+            pl::polars_bail!(ComputeError: "the stream returned a non-struct array");
+        };
+        height += batch.len();
+        for (chunks, &i) in chunks.iter_mut().zip(projection) {
+            chunks.push(batch.values()[i].clone());
+        }
+    }
 
-        // no need to check as a record batch has the same guarantees
-        let df_res: Result<_, String> =
-            Ok(unsafe { DataFrame::new_no_checks_height_from_first(columns) });
-        df_res
-    });
-    let dfs = crate::utils::collect_hinted_result(rb_len, dfs_iter)?;
-    Ok(accumulate_dataframes_vertical_unchecked(dfs))
+    let columns = chunks
+        .into_iter()
+        .zip(projection)
+        .map(|(chunks, &i)| {
+            let field = &fields[i];
+            let s = if chunks.is_empty() {
+                Series::new_empty(field.name.clone(), &dtype_from_arrow_field(field))
+            } else {
+                series_from_arrow(field, chunks)?
+            };
+            Ok(s.into_column())
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let df = DataFrame::new(columns)?;
+    Ok(match n_rows {
+        Some(n_rows) if n_rows < df.height() => df.slice(0, n_rows),
+        _ => df,
+    })
 }
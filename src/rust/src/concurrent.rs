@@ -1,4 +1,5 @@
 //use crate::rdataframe::rseries::ptr_str_to_rseries;
+use crate::rdataframe::read_arrow_stream;
 use crate::rdataframe::RPolarsDataFrame;
 use crate::utils::extendr_concurrent::ParRObj;
 use crate::utils::extendr_concurrent::{concurrent_handler, ThreadCom};
@@ -17,6 +18,9 @@ pub enum RFnSignature {
     FnSeriesToSeries(ParRObj, pl::Series),
     FnTwoSeriesToSeries(ParRObj, pl::Series, pl::Series),
     FnF64ToString(ParRObj, f64),
+    // not an R lambda: the batches of an arrow stream, which may be implemented in R, are
+    // pulled in the main thread. Read as the stream, the projected columns and the max rows.
+    ArrowStreamToDataFrame(ParRObj, Vec<usize>, Option<usize>),
 }
 
 //any possible output from an R lambda
@@ -24,6 +28,7 @@ pub enum RFnSignature {
 pub enum RFnOutput {
     Series(pl::Series),
     String(String),
+    DataFrame(pl::PolarsResult<pl::DataFrame>),
 }
 
 impl RFnSignature {
@@ -58,6 +63,13 @@ impl RFnSignature {
                     .ok_or("wrong return value, expected a string")?;
                 Ok(RFnOutput::String(s.to_string()))
             }
+            RFnSignature::ArrowStreamToDataFrame(stream, projection, n_rows) => {
+                Ok(RFnOutput::DataFrame(read_arrow_stream::pull_batches(
+                    &stream.0,
+                    &projection,
+                    n_rows,
+                )))
+            }
         }
     }
 }
@@ -77,6 +89,13 @@ impl RFnOutput {
             _ => panic!("internal error unexpected type of returned answer"),
         }
     }
+
+    pub fn unwrap_dataframe(self) -> pl::PolarsResult<pl::DataFrame> {
+        match self {
+            RFnOutput::DataFrame(out) => out,
+            _ => panic!("internal error unexpected type of returned answer"),
+        }
+    }
 }
 
 // This is the standard way the main thread which can call the R session,
@@ -179,6 +179,19 @@ fn recursive_robjname2series_tree(x: &Robj, name: &str) -> pl::PolarsResult<Seri
             Ok(SeriesTree::Series(binary_series))
         }
 
+        // a data.frame is a Struct with one field per column
+        Rtype::List if x.inherits("data.frame") => {
+            let l = x.as_list().expect("as matched");
+            let columns: Vec<pl::Series> = collect_hinted_result(
+                l.len(),
+                l.iter().map(|(name, robj)| robjname2series(robj, name)),
+            )?;
+            let height = columns.first().map(|s| s.len()).unwrap_or(0);
+            Ok(SeriesTree::Series(
+                pl::StructChunked::from_series(name.into(), height, columns.iter())?.into_series(),
+            ))
+        }
+
         Rtype::List => {
             // Recusively handle elements of list
             let result_series_vec: pl::PolarsResult<Vec<SeriesTree>> = collect_hinted_result(
@@ -203,7 +216,7 @@ fn recursive_robjname2series_tree(x: &Robj, name: &str) -> pl::PolarsResult<Seri
     };
 
     //post process derived R types
-    match series_result {
+    let series_result = match series_result {
         Ok(SeriesTree::Series(s)) if x.inherits("POSIXct") => {
             let tz = x
                 .get_attrib("tzone")
@@ -284,10 +297,52 @@ fn recursive_robjname2series_tree(x: &Robj, name: &str) -> pl::PolarsResult<Seri
                 (s.cast(&pl::DataType::Int64)? * i_conv).cast(&pl::DataType::Time)?,
             ))
         }
+        // hms also inherits difftime, so it must be matched first
+        Ok(SeriesTree::Series(s)) if x.inherits("hms") => {
+            let ns = scale_to_int64(&s, 1e9)?;
+            let out_of_range = ns
+                .i64()?
+                .into_iter()
+                .flatten()
+                .any(|v| !(0..NS_PER_DAY).contains(&v));
+            if out_of_range {
+                pl::polars_bail!(
+                    ComputeError: "failure to convert class hms to Time, as some values are not between 00:00:00 and 24:00:00"
+                );
+            }
+            Ok(SeriesTree::Series(ns.cast(&pl::DataType::Time)?))
+        }
+        Ok(SeriesTree::Series(s)) if x.inherits("difftime") => {
+            let units = x
+                .get_attrib("units")
+                .and_then(|robj| robj.as_str())
+                .unwrap_or("secs");
+            let secs: f64 = match units {
+                "secs" => 1.0,
+                "mins" => 60.0,
+                "hours" => 3_600.0,
+                "days" => 86_400.0,
+                "weeks" => 604_800.0,
+                _ => pl::polars_bail!(
+                    ComputeError: "failure to convert class difftime as attribute units '{}' is not 'secs', 'mins', 'hours', 'days' or 'weeks'", units
+                ),
+            };
+            Ok(SeriesTree::Series(scale_to_int64(&s, secs * 1e6)?.cast(
+                &pl::DataType::Duration(pl::TimeUnit::Microseconds),
+            )?))
+        }
 
         //     Ok(SeriesTree::Series((s.cast(&pl::DataType::Int64)?*1_000i64).cast(&dt)?))
         // },
         _ => series_result,
+    };
+
+    // each row of an R matrix becomes an element of an Array
+    match (series_result, matrix_dim(x)) {
+        (Ok(SeriesTree::Series(s)), Some((nrow, ncol))) => {
+            Ok(SeriesTree::Series(matrix_to_array(&s, nrow, ncol)?))
+        }
+        (series_result, _) => series_result,
     }
 }
 
@@ -361,3 +416,58 @@ fn robj_to_utf8_series(rstrings: Strings, name: &str) -> pl::Series {
         pl::Series::new(name.into(), s)
     }
 }
+
+const NS_PER_DAY: i64 = 86_400_000_000_000;
+
+// multiply R numbers by a factor and round them to whole counts of a time unit, values that are
+// not finite (NaN, Inf) or do not fit in an i64 become null
+fn scale_to_int64(s: &pl::Series, factor: f64) -> pl::PolarsResult<pl::Series> {
+    const LIMIT: f64 = i64::MAX as f64;
+    let ca: pl::Int64Chunked = s
+        .cast(&pl::DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .map(|opt| {
+            let v = (opt? * factor).round();
+            (v.is_finite() && v.abs() < LIMIT).then_some(v as i64)
+        })
+        .collect();
+    Ok(ca.with_name(s.name().clone()).into_series())
+}
+
+// the number of rows and columns of an R matrix. Only atomic vectors without a class or inheriting
+// matrix or array are matrices, other classed objects with a `dim` (e.g. table) are vectors.
+fn matrix_dim(x: &Robj) -> Option<(usize, usize)> {
+    let is_matrix = match x.class() {
+        None => true,
+        Some(mut class) => class.any(|c| c == "matrix" || c == "array"),
+    };
+    let is_atomic = matches!(
+        x.rtype(),
+        Rtype::Logicals
+            | Rtype::Integers
+            | Rtype::Doubles
+            | Rtype::Complexes
+            | Rtype::Strings
+            | Rtype::Raw
+    );
+    if !(is_matrix && is_atomic) {
+        return None;
+    }
+    match x.get_attrib("dim")?.as_integer_vector()?.as_slice() {
+        [nrow, ncol] => Some((*nrow as usize, *ncol as usize)),
+        _ => None,
+    }
+}
+
+// R matrices are column-major, so the values are gathered row by row before being reshaped
+fn matrix_to_array(s: &pl::Series, nrow: usize, ncol: usize) -> pl::PolarsResult<pl::Series> {
+    let idx = (0..nrow)
+        .flat_map(|i| (0..ncol).map(move |j| (j * nrow + i) as pl::IdxSize))
+        .collect();
+    let idx = pl::IdxCa::from_vec("".into(), idx);
+    s.take(&idx)?.reshape_array(&[
+        pl::ReshapeDimension::new(nrow as i64),
+        pl::ReshapeDimension::new(ncol as i64),
+    ])
+}
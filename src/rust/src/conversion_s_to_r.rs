@@ -5,18 +5,97 @@ use polars::prelude::{self as pl};
 use polars_core::datatypes::DataType;
 use polars_lazy::{dsl::col, frame::IntoLazy};
 
+/// How the dtypes with several representations in R are converted, see `polars_options()`.
+#[derive(Clone, Copy, Debug)]
+pub struct ConversionOptions<'a> {
+    pub int64_conversion: &'a str,
+    pub decimal_conversion: &'a str,
+    pub duration_conversion: DurationConversion,
+    pub time_conversion: TimeConversion,
+    pub array_conversion: &'a str,
+    pub struct_conversion: &'a str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DurationConversion {
+    Double,
+    Difftime,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TimeConversion {
+    PTime,
+    Hms,
+}
+
+impl<'a> ConversionOptions<'a> {
+    pub fn new(
+        int64_conversion: &'a str,
+        decimal_conversion: &'a str,
+        duration_conversion: &str,
+        time_conversion: &str,
+        array_conversion: &'a str,
+        struct_conversion: &'a str,
+    ) -> pl::PolarsResult<Self> {
+        let duration_conversion = match duration_conversion {
+            "double" => DurationConversion::Double,
+            "difftime" => DurationConversion::Difftime,
+            _ => pl::polars_bail!(
+                InvalidOperation: "`duration_conversion` must be one of 'double', 'difftime'"
+            ),
+        };
+        let time_conversion = match time_conversion {
+            "PTime" => TimeConversion::PTime,
+            "hms" => TimeConversion::Hms,
+            _ => pl::polars_bail!(
+                InvalidOperation: "`time_conversion` must be one of 'PTime', 'hms'"
+            ),
+        };
+        Ok(ConversionOptions {
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        })
+    }
+}
+
+// the defaults of `polars_options()`
+impl Default for ConversionOptions<'_> {
+    fn default() -> Self {
+        ConversionOptions {
+            int64_conversion: "double",
+            decimal_conversion: "string",
+            duration_conversion: DurationConversion::Double,
+            time_conversion: TimeConversion::PTime,
+            array_conversion: "list",
+            struct_conversion: "list",
+        }
+    }
+}
+
 //TODO throw a warning if i32 contains a lowerbound value which is the NA in R.
 pub fn pl_series_to_list(
     series: &pl::Series,
     tag_structs: bool,
-    int64_conversion: &str,
+    options: &ConversionOptions,
 ) -> pl::PolarsResult<Robj> {
     use pl::DataType::*;
     fn to_list_recursive(
         s: &pl::Series,
         tag_structs: bool,
-        int64_conversion: &str,
+        options: &ConversionOptions,
     ) -> pl::PolarsResult<Robj> {
+        let ConversionOptions {
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        } = *options;
         match s.dtype() {
             Float64 => s.f64().map(|ca| ca.into_iter().collect_robj()),
             Float32 => s.f32().map(|ca| ca.into_iter().collect_robj()),
@@ -91,6 +170,20 @@ pub fn pl_series_to_list(
                     .map(|opt| opt.map(|val| val as f64))
                     .collect_robj()
             }),
+            // R has no decimal type, the values are kept exactly as strings by default
+            Decimal(_, _) => match decimal_conversion {
+                "string" => s
+                    .cast(&DataType::String)?
+                    .str()
+                    .map(|ca| ca.into_iter().collect_robj()),
+                "double" => s
+                    .cast(&DataType::Float64)?
+                    .f64()
+                    .map(|ca| ca.into_iter().collect_robj()),
+                _ => Err(pl::PolarsError::InvalidOperation(
+                    "`decimal_conversion` must be one of 'string', 'double'".into(),
+                )),
+            },
             String => s.str().map(|ca| ca.into_iter().collect_robj()),
 
             Boolean => s.bool().map(|ca| ca.into_iter().collect_robj()),
@@ -126,8 +219,7 @@ pub fn pl_series_to_list(
                     match opt_s {
                         Some(s) => {
                             let s_ref = s.as_ref();
-                            let inner_val =
-                                to_list_recursive(s_ref, tag_structs, int64_conversion)?;
+                            let inner_val = to_list_recursive(s_ref, tag_structs, options)?;
                             v.push(inner_val);
                         }
 
@@ -141,6 +233,37 @@ pub fn pl_series_to_list(
                 let l = extendr_api::List::from_iter(v.iter());
                 Ok(l.into_robj())
             }
+            Array(_, _) if !matches!(array_conversion, "list" | "matrix") => {
+                Err(pl::PolarsError::InvalidOperation(
+                    "`array_conversion` must be one of 'list', 'matrix'".into(),
+                ))
+            }
+            // each element becomes a row of the matrix, whose values are stored column by column
+            Array(inner, width) if array_conversion == "matrix" && !inner.is_nested() => {
+                let s = s.rechunk();
+                let ca = s.array()?;
+                let (nrow, ncol) = (ca.len(), *width);
+                let valid: Vec<bool> = ca
+                    .is_not_null()
+                    .into_iter()
+                    .map(|opt| opt.unwrap_or(false))
+                    .collect();
+                let idx: pl::IdxCa = (0..ncol)
+                    .flat_map(|j| {
+                        valid.iter().enumerate().map(move |(i, is_valid)| {
+                            is_valid.then_some((i * ncol + j) as pl::IdxSize)
+                        })
+                    })
+                    .collect();
+                let values = ca.get_inner().take(&idx)?;
+                to_list_recursive(&values, tag_structs, options)?
+                    .set_attrib("dim", vec![nrow as i32, ncol as i32])
+                    .map_err(|err| {
+                        pl_error::ComputeError(
+                            format!("when converting polars Array to R matrix: {:?}", err).into(),
+                        )
+                    })
+            }
             Array(_, _) => {
                 let mut v: Vec<extendr_api::Robj> = Vec::with_capacity(s.len());
                 let ca = s.array().unwrap();
@@ -150,8 +273,7 @@ pub fn pl_series_to_list(
                         Some(s) => {
                             let s_ref = s.as_ref();
                             // is safe because s is read to generate new Robj, then discarded.
-                            let inner_val =
-                                to_list_recursive(s_ref, tag_structs, int64_conversion)?;
+                            let inner_val = to_list_recursive(s_ref, tag_structs, options)?;
                             v.push(inner_val);
                         }
 
@@ -165,9 +287,28 @@ pub fn pl_series_to_list(
                 let l = extendr_api::List::from_iter(v.iter());
                 Ok(l.into_robj())
             }
+            Struct(_) if !matches!(struct_conversion, "list" | "data.frame") => {
+                Err(pl::PolarsError::InvalidOperation(
+                    "`struct_conversion` must be one of 'list', 'data.frame'".into(),
+                ))
+            }
             Struct(_) => {
                 let df = s.clone().into_frame().unnest([s.name().clone()]).unwrap();
-                let mut l = RPolarsDataFrame(df).to_list_result(int64_conversion)?;
+                let mut l = RPolarsDataFrame(df).to_list_result(options)?;
+
+                if struct_conversion == "data.frame" {
+                    // the fields become the columns of a data.frame, with one row per value
+                    let row_names: Vec<i32> = (1..=s.len() as i32).collect();
+                    return l
+                        .set_attrib("row.names", row_names)
+                        .and_then(|mut l| l.set_class(["data.frame"]))
+                        .map_err(|err| {
+                            pl_error::ComputeError(
+                                format!("when converting polars Struct to R data.frame: {:?}", err)
+                                    .into(),
+                            )
+                        });
+                }
 
                 //TODO contribute extendr_api set_attrib mutates &self, change signature to surprise anyone
                 if tag_structs {
@@ -185,26 +326,69 @@ pub fn pl_series_to_list(
                 .set_class(&["Date"])
                 .expect("internal error: class label Date failed")),
             Null => Ok((extendr_api::NULL).into_robj()),
-            Time => s
-                .cast(&Int64)?
-                .i64()
-                .map(|ca| {
-                    ca.into_iter()
-                        .map(|opt| opt.map(|val| val as f64))
+            Time => match time_conversion {
+                // hms stores the seconds since midnight
+                TimeConversion::Hms => s
+                    .cast(&Int64)?
+                    .i64()?
+                    .into_iter()
+                    .map(|opt| opt.map(|val| val as f64 / 1_000_000_000.0))
+                    .collect_robj()
+                    .set_class(&["hms", "difftime"])
+                    .and_then(|mut robj| robj.set_attrib("units", "secs"))
+                    .map_err(|err| {
+                        pl_error::ComputeError(
+                            format!("when converting polars Time to R hms: {:?}", err).into(),
+                        )
+                    }),
+                TimeConversion::PTime => s
+                    .cast(&Int64)?
+                    .i64()
+                    .map(|ca| {
+                        ca.into_iter()
+                            .map(|opt| opt.map(|val| val as f64))
+                            .collect_robj()
+                    })
+                    // TODO set_class and set_attrib reallocates the vector, find some way to modify without.
+                    .map(|mut robj| {
+                        robj.set_class(&["PTime"])
+                            .expect("internal error: class label PTime failed")
+                    })
+                    .map(|mut robj| robj.set_attrib("tu", "ns"))
+                    .expect("internal error: attr tu failed")
+                    .map_err(|err| {
+                        pl_error::ComputeError(
+                            format!("when converting polars Time to R PTime: {:?}", err).into(),
+                        )
+                    }),
+            },
+
+            Duration(tu) => {
+                let tu_f64: f64 = match tu {
+                    pl::TimeUnit::Nanoseconds => 1_000_000_000.0,
+                    pl::TimeUnit::Microseconds => 1_000_000.0,
+                    pl::TimeUnit::Milliseconds => 1_000.0,
+                };
+                let ints = s.cast(&Int64)?;
+                let values = ints.i64()?.into_iter();
+                match duration_conversion {
+                    // the number of time units, as polars stores it
+                    DurationConversion::Double => {
+                        Ok(values.map(|opt| opt.map(|val| val as f64)).collect_robj())
+                    }
+                    DurationConversion::Difftime => values
+                        .map(|opt| opt.map(|val| val as f64 / tu_f64))
                         .collect_robj()
-                })
-                // TODO set_class and set_attrib reallocates the vector, find some way to modify without.
-                .map(|mut robj| {
-                    robj.set_class(&["PTime"])
-                        .expect("internal error: class label PTime failed")
-                })
-                .map(|mut robj| robj.set_attrib("tu", "ns"))
-                .expect("internal error: attr tu failed")
-                .map_err(|err| {
-                    pl_error::ComputeError(
-                        format!("when converting polars Time to R PTime: {:?}", err).into(),
-                    )
-                }),
+                        .set_class(&["difftime"])
+                        .and_then(|mut robj| robj.set_attrib("units", "secs"))
+                        .map_err(|err| {
+                            pl_error::ComputeError(
+                                format!("when converting polars Duration to R difftime: {:?}", err)
+                                    .into(),
+                            )
+                        }),
+                }
+            }
 
             Datetime(tu, opt_tz) => {
                 let tu_f64: f64 = match tu {
@@ -273,5 +457,5 @@ pub fn pl_series_to_list(
         }
     }
 
-    to_list_recursive(series, tag_structs, int64_conversion)
+    to_list_recursive(series, tag_structs, options)
 }
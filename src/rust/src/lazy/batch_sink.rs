@@ -0,0 +1,141 @@
+use polars::prelude::{self as pl, CompatLevel};
+use polars_core::utils::arrow::io::ipc::read::{read_file_metadata, FileReader};
+use polars_core::utils::arrow::io::ipc::write::{StreamWriter, WriteOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A writer of the sinks that polars does not have, which receives the output of a query in
+/// batches.
+pub(crate) trait BatchWriter: Send + 'static {
+    fn write_batch(&mut self, df: pl::DataFrame) -> pl::PolarsResult<()>;
+}
+
+/// Execute `lf` with the streaming engine and pass its output to `writer` batch by batch, so the
+/// output does not need to fit in memory. Without `maintain_order`, the batches are written as
+/// the streaming engine produces them, possibly from several threads and in any order. With
+/// `maintain_order`, the engine cannot pass ordered batches to a function, so the output is first
+/// spilled to a temporary Arrow IPC file by the native sink, and the batches are read back from
+/// it in order. Returns the writer, so that it can be finished.
+pub(crate) fn sink_batches<W: BatchWriter>(
+    lf: pl::LazyFrame,
+    maintain_order: bool,
+    writer: W,
+) -> pl::PolarsResult<W> {
+    if maintain_order {
+        return sink_batches_ordered(lf, writer);
+    }
+    let writer = Arc::new(Mutex::new(Some(writer)));
+    let batch_writer = writer.clone();
+    lf.map(
+        move |df| {
+            let empty = df.clear();
+            match lock(&batch_writer)?.as_mut() {
+                Some(writer) => writer.write_batch(df)?,
+                None => unreachable!("the writer is only taken after the query"),
+            }
+            Ok(empty)
+        },
+        pl::OptFlags::STREAMING,
+        None,
+        Some("BATCH SINK"),
+    )
+    .with_streaming(true)
+    .collect()?;
+    let writer = lock(&writer)?.take();
+    Ok(writer.expect("the writer is only taken once"))
+}
+
+fn sink_batches_ordered<W: BatchWriter>(lf: pl::LazyFrame, mut writer: W) -> pl::PolarsResult<W> {
+    let schema = lf.clone().collect_schema()?;
+    let spill = SpillFile::new();
+    let options = pl::IpcWriterOptions {
+        compression: Some(pl::IpcCompression::LZ4),
+        maintain_order: true,
+    };
+    lf.sink_ipc(&spill.0, options, None)?;
+
+    let mut file = std::io::BufReader::new(std::fs::File::open(&spill.0)?);
+    let metadata = read_file_metadata(&mut file)?;
+    let arrow_schema = metadata.schema.clone();
+    for batch in FileReader::new(file, metadata, None, None) {
+        let df = pl::DataFrame::try_from((batch?, arrow_schema.as_ref()))?;
+        // the dtypes that are not restored from the Arrow schema, like the ones of the
+        // categoricals, are cast back to the schema of the query
+        let columns = df
+            .take_columns()
+            .into_iter()
+            .zip(schema.iter_values())
+            .map(|(column, dtype)| column.strict_cast(dtype))
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+        let df = pl::DataFrame::new(columns)?;
+        writer.write_batch(df)?;
+    }
+    Ok(writer)
+}
+
+/// Writes the batches to an Arrow IPC stream, which polars has no sink for.
+pub(crate) struct IpcStreamSink<W: Write> {
+    writer: StreamWriter<W>,
+    compat_level: CompatLevel,
+}
+
+impl<W: Write> IpcStreamSink<W> {
+    pub(crate) fn new(
+        writer: W,
+        schema: &pl::Schema,
+        compression: Option<pl::IpcCompression>,
+        compat_level: CompatLevel,
+    ) -> pl::PolarsResult<Self> {
+        let options = WriteOptions {
+            compression: compression.map(|c| c.into()),
+        };
+        let mut writer = StreamWriter::new(writer, options);
+        writer.start(&schema.to_arrow(compat_level), None)?;
+        Ok(IpcStreamSink {
+            writer,
+            compat_level,
+        })
+    }
+
+    pub(crate) fn finish(mut self) -> pl::PolarsResult<()> {
+        self.writer.finish()
+    }
+}
+
+impl<W: Write + Send + 'static> BatchWriter for IpcStreamSink<W> {
+    fn write_batch(&mut self, mut df: pl::DataFrame) -> pl::PolarsResult<()> {
+        df.align_chunks_par();
+        for batch in df.iter_chunks(self.compat_level, true) {
+            self.writer.write(&batch, None)?;
+        }
+        Ok(())
+    }
+}
+
+// a temporary file that is removed when it is dropped, also when the sink fails or panics
+struct SpillFile(PathBuf);
+
+impl SpillFile {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        SpillFile(std::env::temp_dir().join(format!(
+            "polars-spill-{}-{}.arrow",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> pl::PolarsResult<std::sync::MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| pl::polars_err!(ComputeError: "a writer of the sink panicked"))
+}
@@ -3,10 +3,18 @@ use crate::concurrent::{
 };
 use crate::lazy::dsl::*;
 
-use crate::rdataframe::RPolarsDataFrame as RDF;
-use crate::rdatatype::{new_ipc_compression, new_parquet_compression, RPolarsDataType};
+use crate::lazy::batch_sink::{sink_batches, IpcStreamSink};
+use crate::lazy::partitioned_sink::{sink_partitioned, PartitionOptions, SinkFormat};
+use crate::rdataframe::compression::{sink_compressed, TextFormat};
+use crate::rdataframe::read_arrow_stream::check_no_arrow_stream;
+use crate::rdataframe::write_parquet::sink_with_extras;
+use crate::rdataframe::{create_writeable, RPolarsDataFrame as RDF};
+use crate::rdatatype::{
+    new_ipc_compression, new_parquet_compression, new_text_compression, robj_to_cloud_options,
+    schema_to_pairlist,
+};
 use crate::robj_to;
-use crate::rpolarserr::{polars_to_rpolars_err, RPolarsErr, RResult};
+use crate::rpolarserr::{polars_to_rpolars_err, rerr, RPolarsErr, RResult, WithRctx};
 use crate::utils::try_f64_into_usize;
 use extendr_api::prelude::*;
 use pl::{AsOfOptions, Duration, RollingGroupOptions};
@@ -16,6 +24,7 @@ use polars::prelude::{self as pl};
 use polars::prelude::{JoinCoalesce, SerializeOptions, UnpivotArgsDSL};
 use polars_lazy::prelude::CsvWriterOptions;
 
+use std::path::Path;
 #[allow(unused_imports)]
 use std::result::Result;
 
@@ -77,16 +86,19 @@ impl RPolarsLazyFrame {
         collect_with_r_func_support(self.clone().0)
     }
 
-    pub fn collect_in_background(&self) -> crate::rbackground::RPolarsRThreadHandle<RResult<RDF>> {
+    pub fn collect_in_background(
+        &self,
+    ) -> RResult<crate::rbackground::RPolarsRThreadHandle<RResult<RDF>>> {
         use crate::rbackground::*;
+        check_no_arrow_stream(&self.0, "$collect_in_background()")?;
         let dup = self.clone();
-        RPolarsRThreadHandle::new(move || {
+        Ok(RPolarsRThreadHandle::new(move || {
             Ok(RDF::from(
                 dup.0
                     .collect()
                     .map_err(crate::rpolarserr::polars_to_rpolars_err)?,
             ))
-        })
+        }))
     }
 
     fn serialize(&self) -> RResult<String> {
@@ -110,8 +122,16 @@ impl RPolarsLazyFrame {
         statistics: Robj,
         row_group_size: Robj,
         data_page_size: Robj,
+        extra_options: Robj,
         maintain_order: Robj,
+        storage_options: Robj,
+        partition_by: Robj,
+        max_rows_per_file: Robj,
+        max_bytes_per_file: Robj,
     ) -> RResult<()> {
+        check_no_arrow_stream(&self.0, "$sink_parquet()")?;
+        let path = robj_to!(String, path)?;
+        let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
         let pqwo = polars::prelude::ParquetWriteOptions {
             compression: new_parquet_compression(compression_method, compression_level)?,
             statistics: robj_to!(StatisticsOptions, statistics)?,
@@ -119,20 +139,85 @@ impl RPolarsLazyFrame {
             data_page_size: robj_to!(Option, usize, data_page_size)?,
             maintain_order: robj_to!(bool, maintain_order)?,
         };
+        let extra_options = robj_to!(ParquetExtraOptions, extra_options)?;
+        if let Some(partition_options) = robj_to_partition_options(
+            partition_by,
+            max_rows_per_file,
+            max_bytes_per_file,
+            &storage_options,
+        )? {
+            let format = SinkFormat::Parquet(pqwo, extra_options);
+            return sink_partitioned(self.0.clone(), Path::new(&path), format, partition_options)
+                .map_err(polars_to_rpolars_err);
+        }
+        if !extra_options.is_empty() {
+            let f = create_writeable(&path, &storage_options)?;
+            return sink_with_extras(self.0.clone(), f, &pqwo, &extra_options)
+                .map_err(polars_to_rpolars_err);
+        }
         self.0
             .clone()
-            .sink_parquet(&robj_to!(String, path)?, pqwo, None)
+            .sink_parquet(&path, pqwo, cloud_options)
             .map_err(polars_to_rpolars_err)
     }
 
-    fn sink_ipc(&self, path: Robj, compression: Robj, maintain_order: Robj) -> RResult<()> {
+    fn sink_ipc(
+        &self,
+        path: Robj,
+        compression: Robj,
+        maintain_order: Robj,
+        storage_options: Robj,
+        partition_by: Robj,
+        max_rows_per_file: Robj,
+        max_bytes_per_file: Robj,
+    ) -> RResult<()> {
+        check_no_arrow_stream(&self.0, "$sink_ipc()")?;
+        let path = robj_to!(String, path)?;
+        let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
         let ipcwo = polars::prelude::IpcWriterOptions {
             compression: new_ipc_compression(compression)?,
             maintain_order: robj_to!(bool, maintain_order)?,
         };
+        if let Some(partition_options) = robj_to_partition_options(
+            partition_by,
+            max_rows_per_file,
+            max_bytes_per_file,
+            &storage_options,
+        )? {
+            let format = SinkFormat::Ipc(ipcwo);
+            return sink_partitioned(self.0.clone(), Path::new(&path), format, partition_options)
+                .map_err(polars_to_rpolars_err);
+        }
         self.0
             .clone()
-            .sink_ipc(robj_to!(String, path)?, ipcwo, None)
+            .sink_ipc(path, ipcwo, cloud_options)
+            .map_err(polars_to_rpolars_err)
+    }
+
+    fn sink_ipc_stream(
+        &self,
+        path: Robj,
+        compression: Robj,
+        compat_level: Robj,
+        maintain_order: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        check_no_arrow_stream(&self.0, "$sink_ipc_stream()")?;
+        let f = create_writeable(robj_to!(str, path)?, &storage_options)?;
+        let schema = self
+            .0
+            .clone()
+            .collect_schema()
+            .map_err(polars_to_rpolars_err)?;
+        let writer = IpcStreamSink::new(
+            f,
+            &schema,
+            new_ipc_compression(compression)?,
+            robj_to!(CompatLevel, compat_level)?,
+        )
+        .map_err(polars_to_rpolars_err)?;
+        sink_batches(self.0.clone(), robj_to!(bool, maintain_order)?, writer)
+            .and_then(|writer| writer.finish())
             .map_err(polars_to_rpolars_err)
     }
 
@@ -152,8 +237,17 @@ impl RPolarsLazyFrame {
         float_precision: Robj,
         null_value: Robj,
         quote_style: Robj,
+        compression: Robj,
+        compression_level: Robj,
         maintain_order: Robj,
+        storage_options: Robj,
+        partition_by: Robj,
+        max_rows_per_file: Robj,
+        max_bytes_per_file: Robj,
     ) -> RResult<()> {
+        check_no_arrow_stream(&self.0, "$sink_csv()")?;
+        let path = robj_to!(String, path)?;
+        let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
         // using robj_to!() directly in SerializeOptions doesn't work
         let date_format = robj_to!(Option, String, date_format)?;
         let time_format = robj_to!(Option, String, time_format)?;
@@ -168,6 +262,7 @@ impl RPolarsLazyFrame {
         let include_bom = robj_to!(bool, include_bom)?;
         let maintain_order = robj_to!(bool, maintain_order)?;
         let batch_size = robj_to!(nonzero_usize, batch_size)?;
+        let compression = new_text_compression(compression, compression_level)?;
 
         let serialize_options = SerializeOptions {
             date_format,
@@ -190,19 +285,54 @@ impl RPolarsLazyFrame {
             serialize_options,
         };
 
-        self.0
-            .clone()
-            .sink_csv(robj_to!(String, path)?, options, None)
-            .map_err(polars_to_rpolars_err)
+        if let Some(partition_options) = robj_to_partition_options(
+            partition_by,
+            max_rows_per_file,
+            max_bytes_per_file,
+            &storage_options,
+        )? {
+            let format = SinkFormat::Csv(options, compression);
+            return sink_partitioned(self.0.clone(), Path::new(&path), format, partition_options)
+                .map_err(polars_to_rpolars_err);
+        }
+        match compression {
+            None => self.0.clone().sink_csv(path, options, cloud_options),
+            Some(compression) => sink_compressed(
+                self.0.clone(),
+                &path,
+                TextFormat::Csv(options),
+                compression,
+                cloud_options.as_ref(),
+            ),
+        }
+        .map_err(polars_to_rpolars_err)
     }
 
-    fn sink_json(&self, path: Robj, maintain_order: Robj) -> RResult<()> {
+    fn sink_json(
+        &self,
+        path: Robj,
+        compression: Robj,
+        compression_level: Robj,
+        maintain_order: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        check_no_arrow_stream(&self.0, "$sink_json()")?;
+        let path = robj_to!(String, path)?;
+        let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
+        let compression = new_text_compression(compression, compression_level)?;
         let maintain_order = robj_to!(bool, maintain_order)?;
         let options = pl::JsonWriterOptions { maintain_order };
-        self.0
-            .clone()
-            .sink_json(robj_to!(String, path)?, options, None)
-            .map_err(polars_to_rpolars_err)
+        match compression {
+            None => self.0.clone().sink_json(path, options, cloud_options),
+            Some(compression) => sink_compressed(
+                self.0.clone(),
+                &path,
+                TextFormat::Json(options),
+                compression,
+                cloud_options.as_ref(),
+            ),
+        }
+        .map_err(polars_to_rpolars_err)
     }
 
     fn first(&self) -> Self {
@@ -571,10 +701,7 @@ impl RPolarsLazyFrame {
             .0
             .collect_schema()
             .map_err(crate::rpolarserr::polars_to_rpolars_err)?;
-        let pairs = schema.iter().collect::<Vec<_>>().into_iter();
-        Ok(Pairlist::from_pairs(
-            pairs.map(|(name, ty)| (name, RPolarsDataType(ty.clone()))),
-        ))
+        Ok(schema_to_pairlist(&schema))
     }
 
     fn fetch(&self, n_rows: Robj) -> RResult<RDF> {
@@ -739,6 +866,26 @@ impl RPolarsLazyFrame {
     }
 }
 
+// partitioned output is written to local directories only
+fn robj_to_partition_options(
+    partition_by: Robj,
+    max_rows_per_file: Robj,
+    max_bytes_per_file: Robj,
+    storage_options: &Robj,
+) -> RResult<Option<PartitionOptions>> {
+    let partition_options = PartitionOptions::new(
+        robj_to!(Option, Vec, String, partition_by)?,
+        robj_to!(Option, usize, max_rows_per_file)?,
+        robj_to!(Option, usize, max_bytes_per_file)?,
+    );
+    if partition_options.is_some() && !storage_options.is_null() {
+        return rerr()
+            .bad_arg("storage_options")
+            .plain("is not supported together with partitioned output");
+    }
+    Ok(partition_options)
+}
+
 #[derive(Clone)]
 pub struct RPolarsLazyGroupBy {
     pub lgb: pl::LazyGroupBy,
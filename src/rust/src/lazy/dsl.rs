@@ -86,7 +86,7 @@ impl RPolarsExpr {
             (Rtype::Raw, _) => Ok(dsl::lit(robj_to_binary_vec(robj)?)), // Raw in R is seen as a vector of bytes, in polars it is a Literal, not wrapped in a Series.
             (_, rlen) if rlen != 1 => to_series_then_lit(robj),
             (Rtype::List, _) => to_series_then_lit(robj),
-            (_, rlen) if robj_inherits(&robj, ["POSIXct", "PTime", "Date"]) => {
+            (_, rlen) if robj_inherits(&robj, ["POSIXct", "PTime", "Date", "difftime"]) => {
                 if rlen == 1 {
                     Ok(to_series_then_lit(robj)?.first())
                 } else {
@@ -2464,6 +2464,15 @@ impl RPolarsExpr {
             .into())
     }
 
+    pub fn str_to_decimal(&self, inference_length: Robj) -> RResult<Self> {
+        Ok(self
+            .0
+            .clone()
+            .str()
+            .to_decimal(robj_to!(usize, inference_length)?)
+            .into())
+    }
+
     pub fn str_reverse(&self) -> RResult<Self> {
         Ok(self.0.clone().str().reverse().into())
     }
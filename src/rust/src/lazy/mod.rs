@@ -1,6 +1,8 @@
 use extendr_api::*;
+pub mod batch_sink;
 pub mod dataframe;
 pub mod dsl;
+pub mod partitioned_sink;
 pub mod whenthen;
 
 extendr_module! {
@@ -0,0 +1,357 @@
+use crate::lazy::batch_sink::{sink_batches, BatchWriter};
+use crate::rdataframe::compression::{CompressedWriter, TextCompression};
+use crate::rdataframe::write_parquet::{ParquetExtraOptions, ParquetExtrasWriter};
+use either::Either;
+use polars::prelude::{self as pl, CompatLevel, PlSmallStr, SerWriter};
+use polars_lazy::prelude::CsvWriterOptions;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub(crate) enum SinkFormat {
+    Parquet(pl::ParquetWriteOptions, ParquetExtraOptions),
+    Ipc(pl::IpcWriterOptions),
+    Csv(CsvWriterOptions, Option<TextCompression>),
+}
+
+impl SinkFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            SinkFormat::Parquet(..) => "parquet",
+            SinkFormat::Ipc(_) => "ipc",
+            SinkFormat::Csv(_, None) => "csv",
+            SinkFormat::Csv(_, Some(TextCompression::Gzip(_))) => "csv.gz",
+            SinkFormat::Csv(_, Some(TextCompression::Zstd(_))) => "csv.zst",
+        }
+    }
+
+    fn maintain_order(&self) -> bool {
+        match self {
+            SinkFormat::Parquet(options, _) => options.maintain_order,
+            SinkFormat::Ipc(options) => options.maintain_order,
+            SinkFormat::Csv(options, _) => options.maintain_order,
+        }
+    }
+
+    fn create(&self, path: &Path, df: &pl::DataFrame) -> pl::PolarsResult<OutputFile> {
+        let f = BufWriter::new(File::create(path)?);
+        Ok(match self {
+            SinkFormat::Parquet(options, extras) => {
+                let arrow_schema = df.schema().to_arrow(CompatLevel::newest());
+                let writer =
+                    ParquetExtrasWriter::new(f, df.schema(), arrow_schema, options, extras)?;
+                OutputFile::Parquet(writer)
+            }
+            SinkFormat::Ipc(options) => {
+                let writer = pl::IpcWriter::new(f)
+                    .with_compression(options.compression)
+                    .batched(df.schema())?;
+                OutputFile::Ipc(writer)
+            }
+            SinkFormat::Csv(options, compression) => {
+                let writer = match compression {
+                    None => Either::Left(f),
+                    Some(compression) => Either::Right(compression.writer(f)?),
+                };
+                OutputFile::Csv {
+                    writer,
+                    options: options.clone(),
+                    started: false,
+                }
+            }
+        })
+    }
+}
+
+// a file of the output that is being written
+enum OutputFile {
+    Parquet(ParquetExtrasWriter<BufWriter<File>>),
+    Ipc(polars::io::ipc::BatchedWriter<BufWriter<File>>),
+    // the CSV writers are created per batch, so that the header is only written once and the
+    // compressed stream can be finished
+    Csv {
+        writer: Either<BufWriter<File>, CompressedWriter<BufWriter<File>>>,
+        options: CsvWriterOptions,
+        started: bool,
+    },
+}
+
+impl OutputFile {
+    fn write(&mut self, df: &pl::DataFrame) -> pl::PolarsResult<()> {
+        match self {
+            OutputFile::Parquet(writer) => writer.write(df),
+            OutputFile::Ipc(writer) => writer.write_batch(df),
+            OutputFile::Csv {
+                writer,
+                options,
+                started,
+            } => {
+                let first = !*started;
+                *started = true;
+                csv_writer(writer, options)
+                    .include_bom(options.include_bom && first)
+                    .include_header(options.include_header && first)
+                    .finish(&mut df.clone())
+            }
+        }
+    }
+
+    fn finish(self) -> pl::PolarsResult<()> {
+        match self {
+            OutputFile::Parquet(writer) => writer.finish().map(|_| ()),
+            OutputFile::Ipc(mut writer) => writer.finish(),
+            OutputFile::Csv {
+                writer: Either::Left(mut writer),
+                ..
+            } => Ok(writer.flush()?),
+            OutputFile::Csv {
+                writer: Either::Right(writer),
+                ..
+            } => Ok(writer.finish()?.flush()?),
+        }
+    }
+}
+
+pub(crate) fn csv_writer<W: Write>(writer: W, options: &CsvWriterOptions) -> pl::CsvWriter<W> {
+    let so = &options.serialize_options;
+    pl::CsvWriter::new(writer)
+        .with_separator(so.separator)
+        .with_line_terminator(so.line_terminator.clone())
+        .with_quote_char(so.quote_char)
+        .with_batch_size(options.batch_size)
+        .with_datetime_format(so.datetime_format.clone())
+        .with_date_format(so.date_format.clone())
+        .with_time_format(so.time_format.clone())
+        .with_float_scientific(so.float_scientific)
+        .with_float_precision(so.float_precision)
+        .with_null_value(so.null.clone())
+        .with_quote_style(so.quote_style)
+}
+
+pub(crate) struct PartitionOptions {
+    partition_by: Vec<PlSmallStr>,
+    max_rows_per_file: Option<usize>,
+    max_bytes_per_file: Option<usize>,
+}
+
+impl PartitionOptions {
+    /// Returns `None` if the output is not partitioned.
+    pub(crate) fn new(
+        partition_by: Option<Vec<String>>,
+        max_rows_per_file: Option<usize>,
+        max_bytes_per_file: Option<usize>,
+    ) -> Option<Self> {
+        let partition_by: Vec<PlSmallStr> = partition_by
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+        if partition_by.is_empty() && max_rows_per_file.is_none() && max_bytes_per_file.is_none() {
+            return None;
+        }
+        Some(PartitionOptions {
+            partition_by,
+            max_rows_per_file,
+            max_bytes_per_file,
+        })
+    }
+}
+
+// the number of files that are kept open at the same time, the file of the partition that was
+// written least recently is finished when another one must be opened
+const MAX_OPEN_FILES: usize = 64;
+
+// the files of a hive partition, or of the whole output if it is not partitioned by keys
+struct Partition {
+    n_files: usize,
+    // the number of the last write to the partition
+    last_write: u64,
+    // the file being written, with its number of rows and estimated size in bytes
+    file: Option<(OutputFile, usize, usize)>,
+}
+
+// Routes the batches of the output to the files of their partition, and starts a new file when
+// the current one has reached `max_rows_per_file` or `max_bytes_per_file`. The key columns are
+// kept in the files of all formats, like `write_parquet()` does.
+struct PartitionRouter {
+    base: PathBuf,
+    format: SinkFormat,
+    options: PartitionOptions,
+    partitions: HashMap<PathBuf, Partition>,
+    // the directories of the partitions with an open file, by their last write
+    open_files: BTreeMap<u64, PathBuf>,
+    n_writes: u64,
+}
+
+impl BatchWriter for PartitionRouter {
+    fn write_batch(&mut self, df: pl::DataFrame) -> pl::PolarsResult<()> {
+        if df.height() == 0 {
+            return Ok(());
+        }
+        if self.options.partition_by.is_empty() {
+            let dir = self.base.clone();
+            return self.write_partition(dir, df);
+        }
+        let parts = df.partition_by_stable(self.options.partition_by.iter().cloned(), true)?;
+        for part in parts {
+            let mut dir = self.base.clone();
+            for name in &self.options.partition_by {
+                let key = part.column(name)?;
+                let value = if key.get(0)?.is_null() {
+                    "__HIVE_DEFAULT_PARTITION__".to_string()
+                } else {
+                    let text = key
+                        .as_materialized_series()
+                        .slice(0, 1)
+                        .cast(&pl::DataType::String)?;
+                    encode_partition_value(text.str()?.get(0).unwrap_or_default())
+                };
+                dir.push(format!("{}={}", name, value));
+            }
+            self.write_partition(dir, part)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartitionRouter {
+    fn write_partition(&mut self, dir: PathBuf, mut df: pl::DataFrame) -> pl::PolarsResult<()> {
+        let max_rows = self.options.max_rows_per_file.unwrap_or(usize::MAX).max(1);
+        let max_bytes = self.options.max_bytes_per_file.unwrap_or(usize::MAX);
+        // the size of the rows is estimated from their in-memory size
+        let row_size = (df.estimated_size() / df.height().max(1)).max(1);
+
+        while df.height() > 0 {
+            let is_open = self
+                .partitions
+                .get(&dir)
+                .is_some_and(|partition| partition.file.is_some());
+            if !is_open && self.open_files.len() >= MAX_OPEN_FILES {
+                self.close_least_recent()?;
+            }
+            self.n_writes += 1;
+            let partition = self.partitions.entry(dir.clone()).or_insert(Partition {
+                n_files: 0,
+                last_write: 0,
+                file: None,
+            });
+            if is_open {
+                self.open_files.remove(&partition.last_write);
+            }
+            partition.last_write = self.n_writes;
+            self.open_files.insert(self.n_writes, dir.clone());
+
+            let (file, rows, bytes) = match &mut partition.file {
+                Some(file) => file,
+                None => {
+                    std::fs::create_dir_all(&dir)?;
+                    // use fixed-width file names so that they sort properly, like
+                    // `write_parquet()`
+                    let path = dir.join(format!(
+                        "{:08x}.{}",
+                        partition.n_files,
+                        self.format.extension()
+                    ));
+                    partition.n_files += 1;
+                    partition
+                        .file
+                        .insert((self.format.create(&path, &df)?, 0, 0))
+                }
+            };
+            let room = (max_rows - *rows).min(max_bytes.saturating_sub(*bytes) / row_size);
+            // a file holds at least one row, and is finished when no other row fits in it
+            let n = room.max(usize::from(*rows == 0)).min(df.height());
+            if n > 0 {
+                let (head, tail) = df.split_at(n as i64);
+                file.write(&head)?;
+                *rows += n;
+                *bytes += n * row_size;
+                df = tail;
+            }
+            if n == 0 || *rows >= max_rows || *bytes >= max_bytes {
+                self.open_files.remove(&partition.last_write);
+                if let Some((file, ..)) = partition.file.take() {
+                    file.finish()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn close_least_recent(&mut self) -> pl::PolarsResult<()> {
+        if let Some((_, dir)) = self.open_files.pop_first() {
+            if let Some((file, ..)) = self
+                .partitions
+                .get_mut(&dir)
+                .and_then(|partition| partition.file.take())
+            {
+                file.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    // `empty` is an empty frame with the schema of the files
+    fn finish(&mut self, empty: &pl::DataFrame) -> pl::PolarsResult<()> {
+        // like the native sinks, an empty output that is not partitioned by keys is still
+        // written to a file
+        if self.partitions.is_empty() && self.options.partition_by.is_empty() {
+            std::fs::create_dir_all(&self.base)?;
+            let path = self
+                .base
+                .join(format!("{:08x}.{}", 0, self.format.extension()));
+            let mut file = self.format.create(&path, empty)?;
+            file.write(empty)?;
+            return file.finish();
+        }
+        self.open_files.clear();
+        for partition in self.partitions.values_mut() {
+            if let Some((file, ..)) = partition.file.take() {
+                file.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the output of `lf` into a directory of files. Every combination of the keys
+/// `partition_by` is written to its own hive style `key=value` directory, and the files of a
+/// partition are split to hold at most `max_rows_per_file` rows or about `max_bytes_per_file`
+/// bytes of in-memory data. The batches of the streaming engine are routed to the files as they
+/// are produced, see `sink_batches()`, so the output does not need to fit in memory. At most
+/// `MAX_OPEN_FILES` files are open at the same time: when a batch goes to another partition, the
+/// least recently written file is finished, and the next rows of its partition go to a new file.
+pub(crate) fn sink_partitioned(
+    lf: pl::LazyFrame,
+    base: &Path,
+    format: SinkFormat,
+    options: PartitionOptions,
+) -> pl::PolarsResult<()> {
+    let empty = pl::DataFrame::empty_with_schema(&lf.clone().collect_schema()?);
+    let maintain_order = format.maintain_order();
+    let router = PartitionRouter {
+        base: base.to_path_buf(),
+        format,
+        options,
+        partitions: HashMap::new(),
+        open_files: BTreeMap::new(),
+        n_writes: 0,
+    };
+    let mut router = sink_batches(lf, maintain_order, router)?;
+    router.finish(&empty)
+}
+
+// percent-encode the characters that cannot be used in a hive partition value, like polars does
+pub(crate) fn encode_partition_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if !(0x20..0x7f).contains(&b) || b"/=: %".contains(&b) {
+                format!("%{:02X}", b)
+            } else {
+                (b as char).to_string()
+            }
+        })
+        .collect()
+}
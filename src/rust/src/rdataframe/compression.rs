@@ -0,0 +1,474 @@
+use crate::lazy::batch_sink::{sink_batches, BatchWriter};
+use crate::lazy::partitioned_sink::csv_writer;
+use polars::io::cloud::CloudOptions;
+use polars::io::file_cache::{init_entries_from_uri_list, FileCacheEntry};
+use polars::io::path_utils::{expand_paths, is_cloud_url};
+use polars::io::utils::compression::SupportedCompression;
+use polars::io::RowIndex;
+use polars::lazy::dsl;
+use polars::prelude::{self as pl, Arc, LazyFileListReader, PlSmallStr, SerWriter};
+use polars_core::utils::accumulate_dataframes_vertical_unchecked;
+use polars_lazy::prelude::CsvWriterOptions;
+use polars_plan::plans::ScanSources;
+use polars_utils::mmap::MemSlice;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// The compression of the output of the CSV and NDJSON writers.
+#[derive(Clone, Copy, Debug)]
+pub enum TextCompression {
+    Gzip(u32),
+    Zstd(i32),
+}
+
+impl TextCompression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TextCompression::Gzip(_) => "gz",
+            TextCompression::Zstd(_) => "zst",
+        }
+    }
+
+    pub fn writer<W: Write>(&self, writer: W) -> std::io::Result<CompressedWriter<W>> {
+        Ok(match self {
+            TextCompression::Gzip(level) => CompressedWriter::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::new(*level),
+            )),
+            TextCompression::Zstd(level) => {
+                CompressedWriter::Zstd(zstd::Encoder::new(writer, *level)?)
+            }
+        })
+    }
+}
+
+// The encoders must be finished to write the end of the stream, which a writer that is passed by
+// value to a polars writer cannot be. Pass `&mut` to the polars writer and call `finish()`.
+pub enum CompressedWriter<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn finish(self) -> std::io::Result<W> {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.finish(),
+            CompressedWriter::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// The formats of `sink_compressed()`.
+pub(crate) enum TextFormat {
+    Csv(CsvWriterOptions),
+    Json(pl::JsonWriterOptions),
+}
+
+// Compresses the batches of a sink as they are written, see `sink_batches()`.
+struct CompressedSink {
+    writer: CompressedWriter<BufWriter<Box<dyn Write + Send>>>,
+    format: TextFormat,
+    started: bool,
+}
+
+impl BatchWriter for CompressedSink {
+    fn write_batch(&mut self, mut df: pl::DataFrame) -> pl::PolarsResult<()> {
+        let first = !self.started;
+        self.started = true;
+        match &self.format {
+            // the header is only written before the first batch
+            TextFormat::Csv(options) => csv_writer(&mut self.writer, options)
+                .include_bom(options.include_bom && first)
+                .include_header(options.include_header && first)
+                .finish(&mut df),
+            TextFormat::Json(_) => pl::JsonWriter::new(&mut self.writer)
+                .with_json_format(pl::JsonFormat::JsonLines)
+                .finish(&mut df),
+        }
+    }
+}
+
+/// Write the output of `lf` compressed into `path`. The native sinks cannot compress, so the
+/// batches of the streaming engine are written and compressed as they are produced.
+pub(crate) fn sink_compressed(
+    lf: pl::LazyFrame,
+    path: &str,
+    format: TextFormat,
+    compression: TextCompression,
+    cloud_options: Option<&CloudOptions>,
+) -> pl::PolarsResult<()> {
+    let schema = lf.clone().collect_schema()?;
+    let maintain_order = match &format {
+        TextFormat::Csv(options) => options.maintain_order,
+        TextFormat::Json(options) => options.maintain_order,
+    };
+    let output = polars::io::utils::file::try_get_writeable(path, cloud_options)?;
+    let sink = CompressedSink {
+        writer: compression.writer(BufWriter::new(output))?,
+        format,
+        started: false,
+    };
+    let mut sink = sink_batches(lf, maintain_order, sink)?;
+    // like the native sinks, an empty output still has the header of a CSV file
+    if !sink.started {
+        sink.write_batch(pl::DataFrame::empty_with_schema(&schema))?;
+    }
+    sink.writer.finish()?.flush()?;
+    Ok(())
+}
+
+// A compressed file to scan, either local or on cloud storage
+#[derive(Clone)]
+enum CompressedSource {
+    Local(PathBuf),
+    // a file on cloud storage is downloaded to the file cache of polars, like the native scans
+    // do, and is decompressed from there
+    Cloud(Arc<FileCacheEntry>),
+}
+
+impl CompressedSource {
+    /// Returns `None` if `path` is not compressed. The content of a local file is checked, a file
+    /// on cloud storage is compressed if it has the extension of gzip, zlib or zstd.
+    fn new(path: &Path, cloud_options: Option<&CloudOptions>) -> pl::PolarsResult<Option<Self>> {
+        if is_cloud_url(path) {
+            let uri = path.to_string_lossy();
+            let compressed = [".gz", ".gzip", ".zz", ".zlib", ".zst", ".zstd"]
+                .iter()
+                .any(|ext| uri.ends_with(ext));
+            if !compressed {
+                return Ok(None);
+            }
+            let entries = init_entries_from_uri_list(&[uri.as_ref().into()], cloud_options)?;
+            return Ok(entries.into_iter().next().map(CompressedSource::Cloud));
+        }
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut magic = Vec::with_capacity(4);
+        std::fs::File::open(path)?.take(4).read_to_end(&mut magic)?;
+        Ok(
+            SupportedCompression::check(&magic)
+                .map(|_| CompressedSource::Local(path.to_path_buf())),
+        )
+    }
+
+    fn open(&self) -> pl::PolarsResult<Box<dyn BufRead>> {
+        let file = match self {
+            CompressedSource::Local(path) => std::fs::File::open(path)?,
+            CompressedSource::Cloud(entry) => entry.try_open_check_latest()?,
+        };
+        Ok(maybe_decompress_reader(BufReader::new(file))?)
+    }
+}
+
+/// Wrap `reader` in a decoder if its content is compressed with gzip, zlib or zstd.
+pub fn maybe_decompress_reader<R: BufRead + 'static>(
+    mut reader: R,
+) -> std::io::Result<Box<dyn BufRead>> {
+    let compression = SupportedCompression::check(reader.fill_buf()?);
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(SupportedCompression::GZIP) => {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        }
+        Some(SupportedCompression::ZLIB) => {
+            Box::new(BufReader::new(flate2::bufread::ZlibDecoder::new(reader)))
+        }
+        Some(SupportedCompression::ZSTD) => {
+            Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+        }
+    })
+}
+
+/// A native reader of a text format whose rows can be parsed block by block.
+pub trait BlockReader: LazyFileListReader + Clone + Send + Sync + 'static {
+    /// The reader of a block of whole rows, with the schema of the file. Only the first block of
+    /// a file holds its header and the rows to skip.
+    fn block_reader(self, schema: pl::SchemaRef, first: bool) -> Self;
+}
+
+impl BlockReader for pl::LazyCsvReader {
+    fn block_reader(self, schema: pl::SchemaRef, first: bool) -> Self {
+        let reader = self.with_schema(Some(schema));
+        if first {
+            reader
+        } else {
+            reader
+                .with_has_header(false)
+                .with_skip_rows(0)
+                .with_skip_rows_after_header(0)
+        }
+    }
+}
+
+impl BlockReader for pl::LazyJsonLineReader {
+    fn block_reader(self, schema: pl::SchemaRef, _first: bool) -> Self {
+        self.with_schema(Some(schema))
+    }
+}
+
+/// A native reader of CSV or NDJSON files, with the bytes that delimit their rows.
+#[derive(Clone)]
+pub struct TextReader<R> {
+    pub reader: R,
+    pub eol_char: u8,
+    // an end of line between quotes does not end a row
+    pub quote_char: Option<u8>,
+}
+
+// the size of the blocks of decompressed text that are parsed at once
+const BLOCK_SIZE: usize = 1 << 24;
+
+// Splits the decompressed text of a file into blocks of whole rows.
+struct RowBlocks {
+    reader: Box<dyn BufRead>,
+    eol_char: u8,
+    quote_char: Option<u8>,
+    // the start of the next block, which was read with the previous one
+    rest: Vec<u8>,
+    started: bool,
+    done: bool,
+}
+
+impl RowBlocks {
+    /// Returns the next block, which ends at the end of a row, or at the end of the file. The
+    /// first block is returned even if the file is empty.
+    fn next_block(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+        let first = !self.started;
+        self.started = true;
+        let mut block = std::mem::take(&mut self.rest);
+        let mut scanned = 0;
+        let mut in_quotes = false;
+        let mut row_end = None;
+        loop {
+            let n = self
+                .reader
+                .by_ref()
+                .take(BLOCK_SIZE as u64)
+                .read_to_end(&mut block)?;
+            if n < BLOCK_SIZE {
+                self.done = true;
+                return Ok((first || !block.is_empty()).then_some(block));
+            }
+            for (i, &byte) in block.iter().enumerate().skip(scanned) {
+                if Some(byte) == self.quote_char {
+                    in_quotes = !in_quotes;
+                } else if byte == self.eol_char && !in_quotes {
+                    row_end = Some(i + 1);
+                }
+            }
+            scanned = block.len();
+            // a block is extended until it holds the end of a row
+            if let Some(row_end) = row_end {
+                self.rest = block.split_off(row_end);
+                return Ok(Some(block));
+            }
+        }
+    }
+}
+
+// Decompress the first `n_lines` lines of `source`, or the whole file if `n_lines` is `None`.
+fn decompress_lines(
+    source: &CompressedSource,
+    n_lines: Option<usize>,
+) -> pl::PolarsResult<Vec<u8>> {
+    let mut reader = source.open()?;
+    let mut out = Vec::new();
+    match n_lines {
+        None => {
+            reader.read_to_end(&mut out)?;
+        }
+        Some(n_lines) => {
+            for _ in 0..n_lines {
+                if reader.read_until(b'\n', &mut out)? == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+// The native CSV and NDJSON scans can only memory map plain files, so a compressed file is
+// scanned by an anonymous scan that decompresses it when the query is executed. The decompressed
+// text is parsed by the native reader in blocks of whole rows, so only one block is held in
+// memory besides the parsed rows, and the decompression stops once `n_rows` rows are read.
+struct DecompressedScan<R> {
+    reader: TextReader<R>,
+    source: CompressedSource,
+    schema: pl::SchemaRef,
+}
+
+impl<R: BlockReader> pl::AnonymousScan for DecompressedScan<R> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: pl::AnonymousScanArgs) -> pl::PolarsResult<pl::DataFrame> {
+        // the schema of the plan was inferred from the first lines of the file, the whole file
+        // must have the same dtypes
+        let columns: Vec<PlSmallStr> = match scan_opts.with_columns {
+            Some(columns) => columns.iter().cloned().collect(),
+            None => self.schema.iter_names_cloned().collect(),
+        };
+        let exprs = columns
+            .into_iter()
+            .map(|name| {
+                let dtype = self.schema.try_get(&name)?.clone();
+                Ok(pl::col(name).strict_cast(dtype))
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+
+        let mut blocks = RowBlocks {
+            reader: self.source.open()?,
+            eol_char: self.reader.eol_char,
+            quote_char: self.reader.quote_char,
+            rest: Vec::new(),
+            started: false,
+            done: false,
+        };
+        let mut n_rows = scan_opts.n_rows;
+        let mut dfs = Vec::new();
+        while let Some(block) = blocks.next_block()? {
+            let buffer = MemSlice::from_vec(block);
+            let mut lf = self
+                .reader
+                .reader
+                .clone()
+                .block_reader(self.schema.clone(), dfs.is_empty())
+                .with_sources(ScanSources::Buffers([buffer].into()))
+                .finish()?;
+            if let Some(n_rows) = n_rows {
+                lf = lf.limit(n_rows as pl::IdxSize);
+            }
+            let df = lf.select(exprs.clone()).collect()?;
+            n_rows = n_rows.map(|n| n - df.height());
+            dfs.push(df);
+            if n_rows == Some(0) {
+                break;
+            }
+        }
+        Ok(accumulate_dataframes_vertical_unchecked(dfs))
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> pl::PolarsResult<pl::SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+/// Scan a single file with `reader`. A file compressed with gzip, zlib or zstd is decompressed
+/// when the query is executed. Its schema is inferred from its first `infer_lines` lines, or
+/// from the whole file if `infer_lines` is `None`.
+pub fn scan_file<R: BlockReader>(
+    reader: TextReader<R>,
+    path: &Path,
+    cloud_options: Option<&CloudOptions>,
+    infer_lines: Option<usize>,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let source = CompressedSource::new(path, cloud_options)?;
+    scan_source(reader, path, source, infer_lines)
+}
+
+fn scan_source<R: BlockReader>(
+    reader: TextReader<R>,
+    path: &Path,
+    source: Option<CompressedSource>,
+    infer_lines: Option<usize>,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let Some(source) = source else {
+        return reader
+            .reader
+            .with_paths([path.to_path_buf()].into())
+            .finish();
+    };
+    let head = MemSlice::from_vec(decompress_lines(&source, infer_lines)?);
+    let schema = reader
+        .reader
+        .clone()
+        .with_sources(ScanSources::Buffers([head].into()))
+        .finish()?
+        .collect_schema()?;
+    let scan = DecompressedScan {
+        reader,
+        source,
+        schema,
+    };
+    let args = pl::ScanArgsAnonymous {
+        schema: Some(scan.schema.clone()),
+        name: "DECOMPRESSED SCAN",
+        ..Default::default()
+    };
+    pl::LazyFrame::anonymous_scan(Arc::new(scan), args)
+}
+
+/// Scan `paths` file by file with `scan_file()` if any of them is compressed, and stack the
+/// scans. Returns `None` if no file is compressed, so the caller can use its native scan.
+#[allow(clippy::too_many_arguments)]
+pub fn scan_decompressed<R: BlockReader>(
+    reader: TextReader<R>,
+    paths: &[PathBuf],
+    glob: bool,
+    cloud_options: Option<&CloudOptions>,
+    infer_lines: Option<usize>,
+    n_rows: Option<usize>,
+    row_index: Option<RowIndex>,
+    include_file_paths: Option<PlSmallStr>,
+) -> pl::PolarsResult<Option<pl::LazyFrame>> {
+    let expanded = expand_paths(paths, glob, cloud_options)?;
+    let sources = expanded
+        .iter()
+        .map(|path| CompressedSource::new(path, cloud_options))
+        .collect::<pl::PolarsResult<Vec<_>>>()?;
+    if sources.iter().all(Option::is_none) {
+        return Ok(None);
+    }
+
+    let lfs = expanded
+        .iter()
+        .zip(sources)
+        .map(|(path, source)| {
+            let lf = scan_source(reader.clone(), path, source, infer_lines)?;
+            Ok(match &include_file_paths {
+                Some(name) => {
+                    lf.with_column(pl::lit(path.to_string_lossy().as_ref()).alias(name.clone()))
+                }
+                None => lf,
+            })
+        })
+        .collect::<pl::PolarsResult<Vec<_>>>()?;
+    let mut lf = dsl::concat(lfs, pl::UnionArgs::default())?;
+    if let Some(row_index) = row_index {
+        lf = lf.with_row_index(row_index.name, Some(row_index.offset));
+    }
+    if let Some(n_rows) = n_rows {
+        lf = lf.limit(n_rows as pl::IdxSize);
+    }
+    Ok(Some(lf))
+}
@@ -0,0 +1,1126 @@
+//read and write Delta Lake tables
+
+use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::lazy::partitioned_sink::encode_partition_value;
+use crate::rdataframe::hive_scan::scan_partitioned_files;
+use crate::rdatatype::robj_to_cloud_options;
+use crate::robj_to;
+use crate::rpolarserr::*;
+use crate::utils::robj_to_rchoice;
+
+use extendr_api::{extendr, extendr_module, Robj};
+use polars::io::cloud::CloudOptions;
+use polars::io::file_cache::init_entries_from_uri_list;
+use polars::io::path_utils::{expand_paths, is_cloud_url};
+use polars::io::utils::file::try_get_writeable;
+use polars::io::{pl_async, HiveOptions, RowIndex};
+use polars::prelude::{self as pl, Arc, IntoLazy, PlSmallStr, SerReader, SerWriter};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// A Delta table is a directory of parquet files and a transaction log. Every commit of the log
+// is a JSON file of actions which add and remove data files or change the metadata of the table,
+// and checkpoints summarize all actions up to a version in parquet files. The files of a version
+// are those that were added and not removed up to that version.
+
+const LOG_DIR: &str = "_delta_log";
+
+// the table features of the protocol that are supported when reading and when writing
+const READER_FEATURES: [&str; 1] = ["timestampNtz"];
+const WRITER_FEATURES: [&str; 3] = ["timestampNtz", "appendOnly", "invariants"];
+
+// the fields of the actions read from checkpoints, the others can have any type
+const CHECKPOINT_FIELDS: [(&str, &[&str]); 4] = [
+    ("add", &["path", "partitionValues", "deletionVector"]),
+    ("remove", &["path"]),
+    (
+        "metaData",
+        &[
+            "id",
+            "schemaString",
+            "partitionColumns",
+            "configuration",
+            "createdTime",
+        ],
+    ),
+    (
+        "protocol",
+        &[
+            "minReaderVersion",
+            "minWriterVersion",
+            "readerFeatures",
+            "writerFeatures",
+        ],
+    ),
+];
+
+/// Read a whole local or cloud file.
+pub fn read_file(path: &Path, cloud_options: Option<&CloudOptions>) -> pl::PolarsResult<Vec<u8>> {
+    if !is_cloud_url(path) {
+        return Ok(std::fs::read(path)?);
+    }
+    let uri: Arc<str> = path.to_string_lossy().as_ref().into();
+    let entries = init_entries_from_uri_list(&[uri], cloud_options)?;
+    let mut bytes = Vec::new();
+    entries[0]
+        .try_open_check_latest()?
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+struct DeltaLog {
+    table: String,
+    cloud_options: Option<CloudOptions>,
+    commits: BTreeMap<usize, PathBuf>,
+    // the parts of the checkpoints, with their index and the number of parts
+    checkpoints: BTreeMap<usize, Vec<(usize, usize, PathBuf)>>,
+}
+
+// `<version>.json` is a commit, `<version>.checkpoint.parquet` a checkpoint and
+// `<version>.checkpoint.<part>.<parts>.parquet` one part of a checkpoint
+fn parse_log_file_name(name: &str) -> Option<(usize, Option<(usize, usize)>)> {
+    let (version, rest) = name.split_once('.')?;
+    let version = version.parse().ok()?;
+    match rest.split('.').collect::<Vec<_>>().as_slice() {
+        ["json"] => Some((version, None)),
+        ["checkpoint", "parquet"] => Some((version, Some((1, 1)))),
+        ["checkpoint", part, parts, "parquet"] => {
+            Some((version, Some((part.parse().ok()?, parts.parse().ok()?))))
+        }
+        _ => None,
+    }
+}
+
+impl DeltaLog {
+    /// List the log of the table at `table`. Returns `None` if the table has no log.
+    fn open(table: &str, cloud_options: Option<CloudOptions>) -> pl::PolarsResult<Option<Self>> {
+        let table = table.trim_end_matches('/').to_string();
+        // a glob, as listing a directory fails on files with different extensions
+        let pattern = PathBuf::from(format!("{table}/{LOG_DIR}/*"));
+        let paths = expand_paths(&[pattern], true, cloud_options.as_ref())?;
+
+        let mut commits = BTreeMap::new();
+        let mut checkpoints: BTreeMap<usize, Vec<_>> = BTreeMap::new();
+        for path in paths.iter() {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            match parse_log_file_name(name) {
+                Some((version, None)) => {
+                    commits.insert(version, path.clone());
+                }
+                Some((version, Some((part, parts)))) => {
+                    checkpoints
+                        .entry(version)
+                        .or_default()
+                        .push((part, parts, path.clone()));
+                }
+                None => {}
+            }
+        }
+        if commits.is_empty() && checkpoints.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(DeltaLog {
+            table,
+            cloud_options,
+            commits,
+            checkpoints,
+        }))
+    }
+
+    fn latest_version(&self) -> usize {
+        let commit = self.commits.keys().next_back();
+        let checkpoint = self.checkpoints.keys().next_back();
+        commit.max(checkpoint).copied().unwrap_or(0)
+    }
+
+    // the files of the last checkpoint at or before `version` which has all its parts
+    fn checkpoint(&self, version: usize) -> Option<(usize, Vec<PathBuf>)> {
+        self.checkpoints
+            .range(..=version)
+            .rev()
+            .find_map(|(version, parts)| {
+                let n = parts.first()?.1;
+                let mut parts = parts.clone();
+                parts.sort();
+                parts.dedup_by_key(|(part, _, _)| *part);
+                (parts.len() == n && parts.iter().all(|(_, parts, _)| *parts == n)).then(|| {
+                    (
+                        *version,
+                        parts.into_iter().map(|(_, _, path)| path).collect(),
+                    )
+                })
+            })
+    }
+
+    fn read_file(&self, path: &Path) -> pl::PolarsResult<Vec<u8>> {
+        read_file(path, self.cloud_options.as_ref())
+    }
+
+    fn commit_actions(&self, path: &Path) -> pl::PolarsResult<Vec<Value>> {
+        let bytes = self.read_file(path)?;
+        bytes
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|line| {
+                serde_json::from_slice(line).map_err(
+                    |err| pl::polars_err!(ComputeError: "invalid Delta log '{}': {}", path.display(), err),
+                )
+            })
+            .collect()
+    }
+
+    // the actions of a checkpoint are converted to the JSON of the commits, where the null
+    // actions of a row are structs of null fields
+    fn checkpoint_actions(&self, path: &Path) -> pl::PolarsResult<Vec<Value>> {
+        let bytes = self.read_file(path)?;
+        let df = pl::ParquetReader::new(std::io::Cursor::new(bytes)).finish()?;
+        let exprs: Vec<pl::Expr> = CHECKPOINT_FIELDS
+            .iter()
+            .filter_map(|(action, names)| {
+                let pl::DataType::Struct(fields) = df.schema().get(action)? else {
+                    return None;
+                };
+                let fields: Vec<pl::Expr> = fields
+                    .iter()
+                    .filter(|field| names.contains(&field.name().as_str()))
+                    .map(|field| pl::col(*action).struct_().field_by_name(field.name()))
+                    .collect();
+                (!fields.is_empty()).then(|| pl::as_struct(fields).alias(*action))
+            })
+            .collect();
+        let mut df = df.lazy().select(exprs).collect()?;
+        let mut json = Vec::new();
+        pl::JsonWriter::new(&mut json)
+            .with_json_format(pl::JsonFormat::JsonLines)
+            .finish(&mut df)?;
+        json.split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_slice(line).map_err(|e| pl::polars_err!(ComputeError: "{e}"))
+            })
+            .collect()
+    }
+
+    /// Replay the log up to `version`, the latest version by default.
+    fn snapshot(&self, version: Option<usize>) -> pl::PolarsResult<Snapshot> {
+        let latest = self.latest_version();
+        let version = version.unwrap_or(latest);
+        if version > latest {
+            pl::polars_bail!(
+                ComputeError: "version {} of the Delta table does not exist, the latest version is {}",
+                version, latest
+            );
+        }
+        let mut snapshot = Snapshot {
+            version,
+            ..Default::default()
+        };
+        let start = match self.checkpoint(version) {
+            Some((checkpoint, paths)) => {
+                for path in paths {
+                    for action in self.checkpoint_actions(&path)? {
+                        snapshot.apply(&action)?;
+                    }
+                }
+                checkpoint + 1
+            }
+            None => 0,
+        };
+        for v in start..=version {
+            let Some(path) = self.commits.get(&v) else {
+                pl::polars_bail!(
+                    ComputeError: "version {} of the Delta table is not available, the commit of version {} is missing in the log",
+                    version, v
+                );
+            };
+            for action in self.commit_actions(path)? {
+                snapshot.apply(&action)?;
+            }
+        }
+        if snapshot.metadata.is_none() {
+            pl::polars_bail!(ComputeError: "the Delta log has no metadata up to version {}", version);
+        }
+        Ok(snapshot)
+    }
+
+    // the paths of the data files are URIs, relative to the table
+    fn data_file_path(&self, path: &str) -> PathBuf {
+        let path = percent_decode(path);
+        if path.contains("://") {
+            PathBuf::from(path)
+        } else {
+            PathBuf::from(format!("{}/{}", self.table, path))
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// maps are JSON objects in the commits and lists of key-value structs in the checkpoints
+fn map_entries(value: Option<&Value>) -> Vec<(String, Option<String>)> {
+    let as_string = |value: &Value| match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        value => Some(value.to_string()),
+    };
+    match value {
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(key, value)| (key.clone(), as_string(value)))
+            .collect(),
+        Some(Value::Array(entries)) => entries
+            .iter()
+            .filter_map(|entry| {
+                let key = entry.get("key")?.as_str()?.to_string();
+                Some((key, entry.get("value").and_then(as_string)))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+struct Snapshot {
+    version: usize,
+    protocol: Option<Value>,
+    metadata: Option<Value>,
+    // the data files and their partition values, by path
+    files: BTreeMap<String, Value>,
+}
+
+impl Snapshot {
+    fn apply(&mut self, action: &Value) -> pl::PolarsResult<()> {
+        // the action `name` if its field `key` is set
+        let get = |name: &str, key: &str| {
+            action
+                .get(name)
+                .filter(|value| value.get(key).is_some_and(|value| !value.is_null()))
+        };
+        if let Some(add) = get("add", "path") {
+            let has_deletion_vector = add
+                .get("deletionVector")
+                .and_then(|dv| dv.get("storageType"))
+                .is_some_and(|storage| !storage.is_null());
+            if has_deletion_vector {
+                pl::polars_bail!(ComputeError: "Delta tables with deletion vectors are not supported");
+            }
+            let path = add["path"].as_str().unwrap_or_default().to_string();
+            let partition_values = add.get("partitionValues").cloned();
+            self.files
+                .insert(path, partition_values.unwrap_or(Value::Null));
+        }
+        if let Some(remove) = get("remove", "path") {
+            self.files
+                .remove(remove["path"].as_str().unwrap_or_default());
+        }
+        if let Some(metadata) = get("metaData", "schemaString") {
+            self.metadata = Some(metadata.clone());
+        }
+        if let Some(protocol) = get("protocol", "minReaderVersion") {
+            self.protocol = Some(protocol.clone());
+        }
+        Ok(())
+    }
+
+    fn metadata(&self, key: &str) -> Option<&Value> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(key))
+    }
+
+    fn configuration(&self, key: &str) -> Option<String> {
+        map_entries(self.metadata("configuration"))
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, value)| value)
+    }
+
+    fn partition_columns(&self) -> Vec<PlSmallStr> {
+        string_list(self.metadata("partitionColumns"))
+            .into_iter()
+            .map(PlSmallStr::from)
+            .collect()
+    }
+
+    /// The schema of the table, and the columns that are not nullable.
+    fn schema(&self) -> pl::PolarsResult<(pl::Schema, Vec<PlSmallStr>)> {
+        let schema_string = self
+            .metadata("schemaString")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let schema: Value = serde_json::from_str(schema_string)
+            .map_err(|e| pl::polars_err!(ComputeError: "invalid Delta schema: {e}"))?;
+        let fields = delta_fields(&schema)?;
+        let non_nullable = fields
+            .iter()
+            .filter(|(_, nullable)| !nullable)
+            .map(|(field, _)| field.name().clone())
+            .collect();
+        let schema = fields
+            .into_iter()
+            .map(|(field, _)| (field.name().clone(), field.dtype().clone()))
+            .collect();
+        Ok((schema, non_nullable))
+    }
+
+    // the features are listed from reader version 3 and writer version 7
+    fn check_protocol(&self, write: bool) -> pl::PolarsResult<()> {
+        let protocol = self.protocol.as_ref().unwrap_or(&Value::Null);
+        let (kind, version, features, supported, max_version, features_version) = if write {
+            let version = protocol.get("minWriterVersion").and_then(Value::as_u64);
+            let features = string_list(protocol.get("writerFeatures"));
+            ("writer", version, features, &WRITER_FEATURES[..], 2, 7)
+        } else {
+            let version = protocol.get("minReaderVersion").and_then(Value::as_u64);
+            let features = string_list(protocol.get("readerFeatures"));
+            ("reader", version, features, &READER_FEATURES[..], 2, 3)
+        };
+        let version = version.unwrap_or(1);
+        let unsupported: Vec<&String> = features
+            .iter()
+            .filter(|feature| !supported.contains(&feature.as_str()))
+            .collect();
+        if !(version <= max_version || (version == features_version && unsupported.is_empty())) {
+            pl::polars_bail!(
+                ComputeError: "the Delta table requires {} version {}{}, which is not supported",
+                kind, version,
+                if unsupported.is_empty() { String::new() } else { format!(" with features {:?}", unsupported) }
+            );
+        }
+        // column mapping comes with reader version 2 and writer version 5
+        let column_mapping = self.configuration("delta.columnMapping.mode");
+        if column_mapping.as_deref().is_some_and(|mode| mode != "none") {
+            pl::polars_bail!(ComputeError: "Delta tables with column mapping are not supported");
+        }
+        Ok(())
+    }
+
+    // the partition values of the files, with one row per file
+    fn partition_values(
+        &self,
+        schema: &pl::Schema,
+        partition_columns: &[PlSmallStr],
+    ) -> pl::PolarsResult<pl::DataFrame> {
+        let values: Vec<_> = self
+            .files
+            .values()
+            .map(|values| map_entries(Some(values)))
+            .collect();
+        let columns = partition_columns
+            .iter()
+            .map(|name| {
+                let dtype = schema.get(name).ok_or_else(|| {
+                    pl::polars_err!(
+                        ComputeError: "the partition column '{}' is not in the schema of the Delta table",
+                        name
+                    )
+                })?;
+                let column: Vec<Option<String>> = values
+                    .iter()
+                    .map(|entries| {
+                        entries
+                            .iter()
+                            .find(|(key, _)| key == name.as_str())
+                            .and_then(|(_, value)| value.clone())
+                    })
+                    .collect();
+                pl::Series::new(name.clone(), column)
+                    .strict_cast(dtype)
+                    .map(pl::Column::from)
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+        pl::DataFrame::new(columns)
+    }
+}
+
+// the fields of a Delta struct type, and whether they are nullable
+fn delta_fields(dtype: &Value) -> pl::PolarsResult<Vec<(pl::Field, bool)>> {
+    let fields = dtype
+        .get("fields")
+        .and_then(Value::as_array)
+        .ok_or_else(|| pl::polars_err!(ComputeError: "invalid Delta schema: {}", dtype))?;
+    fields
+        .iter()
+        .map(|field| {
+            let name = field.get("name").and_then(Value::as_str).ok_or_else(
+                || pl::polars_err!(ComputeError: "invalid Delta schema field: {}", field),
+            )?;
+            let dtype = delta_to_polars_dtype(&field["type"])?;
+            let nullable = field.get("nullable").and_then(Value::as_bool);
+            Ok((pl::Field::new(name.into(), dtype), nullable.unwrap_or(true)))
+        })
+        .collect()
+}
+
+/// The Decimal type of a `decimal(<precision>,<scale>)` type of Delta and Iceberg.
+pub fn parse_decimal_type(name: &str) -> Option<pl::DataType> {
+    let (precision, scale) = name
+        .strip_prefix("decimal(")?
+        .strip_suffix(')')?
+        .split_once(',')?;
+    Some(pl::DataType::Decimal(
+        Some(precision.trim().parse().ok()?),
+        Some(scale.trim().parse().ok()?),
+    ))
+}
+
+fn delta_to_polars_dtype(dtype: &Value) -> pl::PolarsResult<pl::DataType> {
+    use pl::DataType as DT;
+    let field = |name: &str, key: &str| {
+        delta_to_polars_dtype(&dtype[key]).map(|dtype| pl::Field::new(name.into(), dtype))
+    };
+    Ok(match dtype {
+        Value::String(name) => match name.as_str() {
+            "boolean" => DT::Boolean,
+            "byte" => DT::Int8,
+            "short" => DT::Int16,
+            "integer" => DT::Int32,
+            "long" => DT::Int64,
+            "float" => DT::Float32,
+            "double" => DT::Float64,
+            "string" => DT::String,
+            "binary" => DT::Binary,
+            "date" => DT::Date,
+            "timestamp" => DT::Datetime(pl::TimeUnit::Microseconds, Some("UTC".into())),
+            "timestamp_ntz" => DT::Datetime(pl::TimeUnit::Microseconds, None),
+            name => parse_decimal_type(name).ok_or_else(
+                || pl::polars_err!(ComputeError: "the Delta type '{}' is not supported", name),
+            )?,
+        },
+        _ => match dtype.get("type").and_then(Value::as_str) {
+            Some("struct") => DT::Struct(
+                delta_fields(dtype)?
+                    .into_iter()
+                    .map(|(field, _)| field)
+                    .collect(),
+            ),
+            Some("array") => DT::List(Box::new(delta_to_polars_dtype(&dtype["elementType"])?)),
+            // a map is a list of key-value structs, like in parquet files
+            Some("map") => DT::List(Box::new(DT::Struct(vec![
+                field("key", "keyType")?,
+                field("value", "valueType")?,
+            ]))),
+            _ => pl::polars_bail!(ComputeError: "invalid Delta type: {}", dtype),
+        },
+    })
+}
+
+// the Delta type of a polars type, and the type the values are cast to before they are written
+fn polars_to_delta_dtype(dtype: &pl::DataType) -> pl::PolarsResult<(Value, pl::DataType)> {
+    use pl::DataType as DT;
+    let (name, cast) = match dtype {
+        DT::Boolean => ("boolean", DT::Boolean),
+        DT::Int8 => ("byte", DT::Int8),
+        DT::Int16 | DT::UInt8 => ("short", DT::Int16),
+        DT::Int32 | DT::UInt16 => ("integer", DT::Int32),
+        DT::Int64 | DT::UInt32 | DT::UInt64 => ("long", DT::Int64),
+        DT::Float32 => ("float", DT::Float32),
+        DT::Float64 => ("double", DT::Float64),
+        DT::String | DT::Categorical(..) | DT::Enum(..) => ("string", DT::String),
+        DT::Binary => ("binary", DT::Binary),
+        DT::Date => ("date", DT::Date),
+        DT::Datetime(_, Some(_)) => (
+            "timestamp",
+            DT::Datetime(pl::TimeUnit::Microseconds, Some("UTC".into())),
+        ),
+        DT::Datetime(_, None) => (
+            "timestamp_ntz",
+            DT::Datetime(pl::TimeUnit::Microseconds, None),
+        ),
+        DT::Decimal(precision, scale) => {
+            let (precision, scale) = (precision.unwrap_or(38), scale.unwrap_or(0));
+            let name = format!("decimal({},{})", precision, scale);
+            return Ok((name.into(), DT::Decimal(Some(precision), Some(scale))));
+        }
+        DT::List(inner) | DT::Array(inner, _) => {
+            let (element, inner) = polars_to_delta_dtype(inner)?;
+            let dtype = json!({"type": "array", "elementType": element, "containsNull": true});
+            return Ok((dtype, DT::List(Box::new(inner))));
+        }
+        DT::Struct(fields) => {
+            let (dtype, fields) =
+                polars_to_delta_struct(fields.iter().map(|field| (field.name(), field.dtype())))?;
+            return Ok((dtype, DT::Struct(fields)));
+        }
+        dtype => pl::polars_bail!(
+            ComputeError: "the data type {} cannot be written to a Delta table", dtype
+        ),
+    };
+    Ok((name.into(), cast))
+}
+
+fn polars_to_delta_struct<'a>(
+    fields: impl Iterator<Item = (&'a PlSmallStr, &'a pl::DataType)>,
+) -> pl::PolarsResult<(Value, Vec<pl::Field>)> {
+    let mut delta_fields = Vec::new();
+    let mut cast_fields = Vec::new();
+    for (name, dtype) in fields {
+        let (delta_dtype, cast) = polars_to_delta_dtype(dtype)?;
+        delta_fields.push(json!({
+            "name": name.as_str(),
+            "type": delta_dtype,
+            "nullable": true,
+            "metadata": {},
+        }));
+        cast_fields.push(pl::Field::new(name.clone(), cast));
+    }
+    Ok((
+        json!({"type": "struct", "fields": delta_fields}),
+        cast_fields,
+    ))
+}
+
+// a data file is read with the types of the table, the columns that were added to the table
+// after the file was written are null
+fn scan_data_file(
+    path: &Path,
+    args: &pl::ScanArgsParquet,
+    schema: &pl::Schema,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let mut lf = pl::LazyFrame::scan_parquet(path, args.clone())?;
+    let available = lf.collect_schema()?;
+    let exprs = schema
+        .iter()
+        .map(|(name, dtype)| {
+            if available.contains(name) {
+                pl::col(name.clone()).strict_cast(dtype.clone())
+            } else {
+                pl::lit(pl::Null {}).cast(dtype.clone()).alias(name.clone())
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(lf.select(exprs))
+}
+
+fn scan_delta(
+    table: &str,
+    version: Option<usize>,
+    args: pl::ScanArgsParquet,
+    n_rows: Option<usize>,
+    row_index: Option<RowIndex>,
+    include_file_paths: Option<PlSmallStr>,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let log = DeltaLog::open(table, args.cloud_options.clone())?
+        .ok_or_else(|| pl::polars_err!(ComputeError: "no Delta table found at '{}'", table))?;
+    let snapshot = log.snapshot(version)?;
+    snapshot.check_protocol(false)?;
+    let (schema, _) = snapshot.schema()?;
+    let partition_columns = snapshot.partition_columns();
+
+    let partitions = snapshot.partition_values(&schema, &partition_columns)?;
+    let files = snapshot
+        .files
+        .keys()
+        .enumerate()
+        .map(|(i, path)| (log.data_file_path(path), partitions.slice(i as i64, 1)))
+        .collect();
+
+    // the partition columns are appended to the columns of the data files by the scan
+    let data_schema: pl::Schema = schema
+        .iter()
+        .filter(|(name, _)| !partition_columns.contains(name))
+        .map(|(name, dtype)| (name.clone(), dtype.clone()))
+        .collect();
+    let mut scan_schema = data_schema.clone();
+    for column in partitions.get_columns() {
+        scan_schema.with_column(column.name().clone(), column.dtype().clone());
+    }
+    if let Some(name) = &include_file_paths {
+        scan_schema.with_column(name.clone(), pl::DataType::String);
+    }
+    let order: Vec<pl::Expr> = row_index
+        .iter()
+        .map(|row_index| row_index.name.clone())
+        .chain(schema.iter_names_cloned())
+        .chain(include_file_paths.iter().cloned())
+        .map(pl::col)
+        .collect();
+
+    let lf = scan_partitioned_files(
+        files,
+        Arc::new(scan_schema),
+        n_rows,
+        row_index,
+        include_file_paths,
+        "DELTA SCAN",
+        move |path| scan_data_file(path, &args, &data_schema),
+    )?;
+    Ok(lf.select(order))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn new_from_delta(
+    path: Robj,
+    version: Robj,
+    n_rows: Robj,
+    row_index_name: Robj,
+    row_index_offset: Robj,
+    parallel: Robj,
+    use_statistics: Robj,
+    low_memory: Robj,
+    storage_options: Robj,
+    include_file_paths: Robj,
+) -> RResult<RPolarsLazyFrame> {
+    let path = robj_to!(String, path)?;
+    let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
+    let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
+    let row_index = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+    // every data file is scanned on its own, with the partition values from the log
+    let args = pl::ScanArgsParquet {
+        parallel: robj_to!(ParallelStrategy, parallel)?,
+        use_statistics: robj_to!(bool, use_statistics)?,
+        low_memory: robj_to!(bool, low_memory)?,
+        cloud_options,
+        hive_options: HiveOptions {
+            enabled: Some(false),
+            ..Default::default()
+        },
+        glob: false,
+        ..Default::default()
+    };
+    scan_delta(
+        &path,
+        robj_to!(Option, usize, version)?,
+        args,
+        robj_to!(Option, usize, n_rows)?,
+        row_index,
+        robj_to!(Option, String, include_file_paths)?.map(|x| x.into()),
+    )
+    .map_err(polars_to_rpolars_err)
+    .map(RPolarsLazyFrame)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeltaWriteMode {
+    Error,
+    Append,
+    Overwrite,
+    Ignore,
+}
+
+pub fn robj_to_delta_write_mode(robj: Robj) -> RResult<DeltaWriteMode> {
+    match robj_to_rchoice(robj)?.as_str() {
+        "error" => Ok(DeltaWriteMode::Error),
+        "append" => Ok(DeltaWriteMode::Append),
+        "overwrite" => Ok(DeltaWriteMode::Overwrite),
+        "ignore" => Ok(DeltaWriteMode::Ignore),
+        s => rerr().notachoice(format!(
+            "mode ('{s}') must be one of 'error', 'append', 'overwrite' or 'ignore'"
+        )),
+    }
+}
+
+fn random_uuid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn new_metadata(
+    id: &str,
+    schema_string: &str,
+    partition_columns: &[PlSmallStr],
+    configuration: Value,
+    created_time: u64,
+) -> Value {
+    let partition_columns: Vec<&str> = partition_columns.iter().map(|c| c.as_str()).collect();
+    json!({
+        "id": id,
+        "format": {"provider": "parquet", "options": {}},
+        "schemaString": schema_string,
+        "partitionColumns": partition_columns,
+        "configuration": configuration,
+        "createdTime": created_time,
+    })
+}
+
+// the protocol of a table, with the timestampNtz feature if it is required by the schema
+fn protocol_for(current: Option<&Value>, schema_string: &str) -> Option<Value> {
+    let has_feature = |key: &str| {
+        current.is_some_and(|protocol| {
+            string_list(protocol.get(key)).contains(&"timestampNtz".to_string())
+        })
+    };
+    if !schema_string.contains("\"timestamp_ntz\"") || has_feature("readerFeatures") {
+        return current
+            .is_none()
+            .then(|| json!({"minReaderVersion": 1, "minWriterVersion": 2}));
+    }
+    let mut reader_features = current
+        .map(|protocol| string_list(protocol.get("readerFeatures")))
+        .unwrap_or_default();
+    let mut writer_features = current
+        .map(|protocol| string_list(protocol.get("writerFeatures")))
+        .unwrap_or_default();
+    // the features of a legacy writer version must be listed from version 7
+    let writer_version = current.and_then(|protocol| protocol.get("minWriterVersion")?.as_u64());
+    if writer_version.is_some_and(|version| version == 2) {
+        writer_features.extend(["appendOnly".to_string(), "invariants".to_string()]);
+    }
+    reader_features.push("timestampNtz".to_string());
+    writer_features.push("timestampNtz".to_string());
+    writer_features.dedup();
+    Some(json!({
+        "minReaderVersion": 3,
+        "minWriterVersion": 7,
+        "readerFeatures": reader_features,
+        "writerFeatures": writer_features,
+    }))
+}
+
+fn already_written(path: &str) -> pl::PolarsError {
+    pl::polars_err!(ComputeError: "'{}' was written by another writer of the Delta table", path)
+}
+
+// put an object that must not exist yet, with a precondition of the object store
+fn put_new_object(
+    path: &str,
+    bytes: &[u8],
+    cloud_options: Option<&CloudOptions>,
+) -> pl::PolarsResult<()> {
+    use object_store::{ObjectStore, PutMode, PutOptions, PutPayload};
+    let (scheme, rest) = path.split_once("://").unwrap_or_default();
+    let key = rest.split_once('/').map(|(_, key)| key).unwrap_or_default();
+    let location = object_store::path::Path::from(key);
+    let options = cloud_options.cloned().unwrap_or_default();
+    let put_options = PutOptions {
+        mode: PutMode::Create,
+        ..Default::default()
+    };
+    let payload = PutPayload::from(bytes.to_vec());
+    let result = pl_async::get_runtime().block_on_potential_spawn(async {
+        pl::PolarsResult::Ok(match scheme {
+            "s3" | "s3a" => {
+                let store = options.build_aws(path).await?;
+                store.put_opts(&location, payload, put_options).await
+            }
+            "az" | "azure" | "abfs" | "abfss" | "adl" => {
+                let store = options.build_azure(path)?;
+                store.put_opts(&location, payload, put_options).await
+            }
+            "gs" | "gcs" | "gcp" => {
+                let store = options.build_gcp(path)?;
+                store.put_opts(&location, payload, put_options).await
+            }
+            _ => pl::polars_bail!(
+                ComputeError: "committing to a Delta table at '{}' is not supported", path
+            ),
+        })
+    })?;
+    match result {
+        Ok(_) => Ok(()),
+        Err(object_store::Error::AlreadyExists { .. })
+        | Err(object_store::Error::Precondition { .. }) => Err(already_written(path)),
+        Err(object_store::Error::NotImplemented) => pl::polars_bail!(
+            ComputeError: "the object store of '{}' cannot create a file only if it does not exist, which is needed to commit to a Delta table. On S3, set `aws_conditional_put = \"etag\"` in `storage_options`",
+            path
+        ),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// a commit is only created if its version does not exist yet, so that concurrent writers of a
+// table cannot replace each other's commits
+fn write_file(
+    path: &str,
+    bytes: &[u8],
+    cloud_options: Option<&CloudOptions>,
+    exclusive: bool,
+) -> pl::PolarsResult<()> {
+    if is_cloud_url(path) {
+        if exclusive {
+            return put_new_object(path, bytes, cloud_options);
+        }
+        let mut writer = try_get_writeable(path, cloud_options)?;
+        writer.write_all(bytes)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    if let Some(dir) = Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    if exclusive {
+        options.write(true).create_new(true);
+    } else {
+        options.write(true).create(true).truncate(true);
+    }
+    let mut file = options.open(path).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::AlreadyExists {
+            already_written(path)
+        } else {
+            err.into()
+        }
+    })?;
+    file.write_all(bytes)?;
+    Ok(())
+}
+
+// the partition values are written as strings and parsed with the type of their column when the
+// table is read, which is only lossless for some types
+fn check_partition_columns(
+    partition_columns: &[PlSmallStr],
+    schema: &pl::Schema,
+) -> pl::PolarsResult<()> {
+    use pl::DataType as DT;
+    for name in partition_columns {
+        match schema.get(name) {
+            None => pl::polars_bail!(
+                ColumnNotFound: "the partition column '{}' is not a column of the DataFrame", name
+            ),
+            Some(dtype) if !(dtype.is_integer() || matches!(dtype, DT::String | DT::Date)) => {
+                pl::polars_bail!(
+                    ComputeError: "the column '{}' of type {} cannot partition a Delta table, only integer, string and date columns can",
+                    name, dtype
+                )
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+// the path of a data file is a URI relative to the table
+fn encode_uri_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~/=".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+/// Write `df` to the Delta table at `table`. A new table is partitioned by `partition_by`. When
+/// rows are appended, the table keeps its partition columns, which `partition_by` must match if
+/// it is given. When the table is overwritten, it is partitioned by `partition_by`, or keeps its
+/// partition columns if `partition_by` is `None`. Every partition is written to its own data
+/// file, in a hive style `key=value` directory.
+pub fn write_delta(
+    df: &pl::DataFrame,
+    table: &str,
+    mode: DeltaWriteMode,
+    partition_by: Option<Vec<String>>,
+    cloud_options: Option<CloudOptions>,
+) -> pl::PolarsResult<()> {
+    let table = table.trim_end_matches('/');
+    let snapshot = match DeltaLog::open(table, cloud_options.clone())? {
+        Some(log) => Some(log.snapshot(None)?),
+        None => None,
+    };
+    let partition_by: Option<Vec<PlSmallStr>> =
+        partition_by.map(|names| names.into_iter().map(PlSmallStr::from).collect());
+    let (schema, fields) = polars_to_delta_struct(df.schema().iter())?;
+    let schema_string = schema.to_string();
+    let exprs: Vec<pl::Expr> = fields
+        .iter()
+        .map(|field| pl::col(field.name().clone()).strict_cast(field.dtype().clone()))
+        .collect();
+    let mut df = df.clone().lazy().select(exprs).collect()?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default();
+    let operation_mode = format!("{:?}", mode);
+    let mut actions = vec![json!({"commitInfo": {
+        "timestamp": now,
+        "operation": "WRITE",
+        "operationParameters": {"mode": operation_mode},
+        "engineInfo": "polars (R)",
+    }})];
+
+    let (version, partition_columns) = match &snapshot {
+        None => {
+            let partition_columns = partition_by.unwrap_or_default();
+            check_partition_columns(&partition_columns, &df.schema())?;
+            actions.extend(protocol_for(None, &schema_string).map(|p| json!({"protocol": p})));
+            let metadata = new_metadata(
+                &random_uuid(),
+                &schema_string,
+                &partition_columns,
+                json!({}),
+                now,
+            );
+            actions.push(json!({"metaData": metadata}));
+            (0, partition_columns)
+        }
+        Some(snapshot) => {
+            let table_partition_columns = snapshot.partition_columns();
+            let partition_columns = match mode {
+                DeltaWriteMode::Error => {
+                    pl::polars_bail!(ComputeError: "the Delta table at '{}' already exists", table)
+                }
+                DeltaWriteMode::Ignore => return Ok(()),
+                DeltaWriteMode::Append => {
+                    snapshot.check_protocol(true)?;
+                    if partition_by
+                        .as_ref()
+                        .is_some_and(|names| *names != table_partition_columns)
+                    {
+                        pl::polars_bail!(
+                            ComputeError: "the Delta table is partitioned by {:?}, rows appended to it cannot be partitioned by other columns",
+                            table_partition_columns
+                        );
+                    }
+                    let (table_schema, non_nullable) = snapshot.schema()?;
+                    let schema = df.schema();
+                    let matches = table_schema.len() == schema.len()
+                        && table_schema
+                            .iter()
+                            .all(|(name, dtype)| schema.get(name) == Some(dtype));
+                    if !matches {
+                        pl::polars_bail!(
+                            SchemaMismatch: "the schema of the DataFrame does not match the schema of the Delta table\n\nDataFrame: {:?}\nDelta table: {:?}",
+                            schema, table_schema
+                        );
+                    }
+                    for name in non_nullable {
+                        if df.column(name.as_str())?.null_count() > 0 {
+                            pl::polars_bail!(ComputeError: "the column '{}' of the Delta table cannot contain null values", name);
+                        }
+                    }
+                    df = df.select(table_schema.iter_names_cloned())?;
+                    table_partition_columns
+                }
+                DeltaWriteMode::Overwrite => {
+                    snapshot.check_protocol(true)?;
+                    if snapshot.configuration("delta.appendOnly").as_deref() == Some("true") {
+                        pl::polars_bail!(ComputeError: "the Delta table is append-only and cannot be overwritten");
+                    }
+                    let partition_columns = partition_by.unwrap_or(table_partition_columns);
+                    check_partition_columns(&partition_columns, &df.schema())?;
+                    let protocol = protocol_for(snapshot.protocol.as_ref(), &schema_string);
+                    actions.extend(protocol.map(|p| json!({"protocol": p})));
+                    let id = snapshot.metadata("id").and_then(Value::as_str);
+                    let configuration: serde_json::Map<String, Value> =
+                        map_entries(snapshot.metadata("configuration"))
+                            .into_iter()
+                            .map(|(key, value)| (key, value.into()))
+                            .collect();
+                    let created_time = snapshot.metadata("createdTime").and_then(Value::as_u64);
+                    let metadata = new_metadata(
+                        id.map(String::from).unwrap_or_else(random_uuid).as_str(),
+                        &schema_string,
+                        &partition_columns,
+                        configuration.into(),
+                        created_time.unwrap_or(now),
+                    );
+                    actions.push(json!({"metaData": metadata}));
+                    for path in snapshot.files.keys() {
+                        actions.push(json!({"remove": {
+                            "path": path,
+                            "deletionTimestamp": now,
+                            "dataChange": true,
+                        }}));
+                    }
+                    partition_columns
+                }
+            };
+            (snapshot.version + 1, partition_columns)
+        }
+    };
+
+    // the partition columns are not written to the data files, their values are in the log
+    let parts = if partition_columns.is_empty() {
+        vec![df]
+    } else {
+        df.partition_by_stable(partition_columns.iter().cloned(), true)?
+    };
+    // the data files are written before the commit which adds them to the table
+    for part in parts {
+        let mut dir = String::new();
+        let mut partition_values = serde_json::Map::new();
+        for name in &partition_columns {
+            let value = part
+                .column(name)?
+                .as_materialized_series()
+                .slice(0, 1)
+                .cast(&pl::DataType::String)?;
+            let value = value.str()?.get(0).map(String::from);
+            let encoded = value.as_deref().map_or(
+                "__HIVE_DEFAULT_PARTITION__".to_string(),
+                encode_partition_value,
+            );
+            dir.push_str(&format!("{}={}/", name, encoded));
+            partition_values.insert(name.to_string(), value.map_or(Value::Null, Value::from));
+        }
+        let mut data = part.drop_many(partition_columns.iter().cloned());
+        let name = format!("{dir}part-00000-{}-c000.zstd.parquet", random_uuid());
+        let mut buf = Vec::new();
+        pl::ParquetWriter::new(&mut buf)
+            .with_compression(pl::ParquetCompression::Zstd(None))
+            .finish(&mut data)?;
+        write_file(
+            &format!("{table}/{name}"),
+            &buf,
+            cloud_options.as_ref(),
+            false,
+        )?;
+        actions.push(json!({"add": {
+            "path": encode_uri_path(&name),
+            "partitionValues": partition_values,
+            "size": buf.len(),
+            "modificationTime": now,
+            "dataChange": true,
+        }}));
+    }
+
+    let commit: String = actions
+        .iter()
+        .map(|action| format!("{}\n", action))
+        .collect();
+    write_file(
+        &format!("{table}/{LOG_DIR}/{version:020}.json"),
+        commit.as_bytes(),
+        cloud_options.as_ref(),
+        true,
+    )
+}
+
+extendr_module! {
+    mod delta;
+    fn new_from_delta;
+}
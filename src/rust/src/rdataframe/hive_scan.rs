@@ -0,0 +1,299 @@
+use polars::io::cloud::CloudOptions;
+use polars::io::csv::read::schema_inference::{finish_infer_field_schema, infer_field_schema};
+use polars::io::path_utils::{expand_paths_hive, expanded_from_single_directory};
+use polars::io::{HiveOptions, RowIndex};
+use polars::lazy::dsl;
+use polars::prelude::{self as pl, Arc, IntoLazy, PlHashSet, PlSmallStr};
+use std::path::{Path, PathBuf};
+
+type ScanFile = Box<dyn Fn(&Path) -> pl::PolarsResult<pl::LazyFrame> + Send + Sync>;
+
+// The native CSV and NDJSON scans do not support hive partitioning. This anonymous scan reads
+// every file with the native scan, appends the partition values parsed from its path and skips
+// files whose partition values do not pass the pushed down predicate.
+struct HiveScan {
+    files: Vec<(PathBuf, pl::DataFrame)>,
+    schema: pl::SchemaRef,
+    include_file_paths: Option<PlSmallStr>,
+    scan_file: ScanFile,
+}
+
+impl HiveScan {
+    fn is_pruned(partitions: &pl::DataFrame, predicates: &[pl::Expr]) -> bool {
+        // predicates referring to other columns fail to evaluate and never prune a file
+        predicates.iter().any(|predicate| {
+            partitions
+                .clone()
+                .lazy()
+                .select([predicate.clone()])
+                .collect()
+                .ok()
+                .and_then(|df| df.select_at_idx(0).and_then(|c| c.bool().ok()?.get(0)))
+                .is_some_and(|keep| !keep)
+        })
+    }
+
+    fn scan_one(&self, path: &Path, partitions: &pl::DataFrame) -> pl::PolarsResult<pl::LazyFrame> {
+        let mut exprs = partitions
+            .get_columns()
+            .iter()
+            .map(|c| {
+                let value = pl::Scalar::new(c.dtype().clone(), c.get(0)?.into_static());
+                Ok(pl::lit(value).alias(c.name().clone()))
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+        if let Some(name) = &self.include_file_paths {
+            exprs.push(pl::lit(path.to_string_lossy().as_ref()).alias(name.clone()));
+        }
+        Ok((self.scan_file)(path)?.with_columns(exprs))
+    }
+}
+
+impl pl::AnonymousScan for HiveScan {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: pl::AnonymousScanArgs) -> pl::PolarsResult<pl::DataFrame> {
+        let mut predicates = Vec::new();
+        if let Some(predicate) = &scan_opts.predicate {
+            split_conjunction(predicate, &mut predicates);
+        }
+        let columns: Option<Vec<pl::Expr>> = scan_opts
+            .with_columns
+            .as_ref()
+            .map(|columns| columns.iter().map(|name| pl::col(name.clone())).collect());
+
+        let lfs = self
+            .files
+            .iter()
+            .filter(|(_, partitions)| !Self::is_pruned(partitions, &predicates))
+            .map(|(path, partitions)| {
+                let mut lf = self.scan_one(path, partitions)?;
+                if let Some(predicate) = &scan_opts.predicate {
+                    lf = lf.filter(predicate.clone());
+                }
+                if let Some(columns) = &columns {
+                    lf = lf.select(columns);
+                }
+                Ok(lf)
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+
+        if lfs.is_empty() {
+            let schema = scan_opts.output_schema.unwrap_or(scan_opts.schema);
+            return Ok(pl::DataFrame::empty_with_schema(&schema));
+        }
+        let mut lf = dsl::concat(lfs, pl::UnionArgs::default())?;
+        if let Some(n_rows) = scan_opts.n_rows {
+            lf = lf.limit(n_rows as pl::IdxSize);
+        }
+        lf.collect()
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> pl::PolarsResult<pl::SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_predicate_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+fn split_conjunction(expr: &pl::Expr, out: &mut Vec<pl::Expr>) {
+    match expr {
+        pl::Expr::BinaryExpr {
+            left,
+            op: pl::Operator::And | pl::Operator::LogicalAnd,
+            right,
+        } => {
+            split_conjunction(left, out);
+            split_conjunction(right, out);
+        }
+        _ => out.push(expr.clone()),
+    }
+}
+
+// parse the `key=value` directories of a path, starting from `start_idx`
+fn parse_partitions(path: &Path, start_idx: usize) -> Vec<(String, Option<String>)> {
+    let path = path.to_string_lossy();
+    let relative = Path::new(path.get(start_idx..).unwrap_or_default());
+    relative
+        .parent()
+        .into_iter()
+        .flat_map(|dir| dir.components())
+        .filter_map(|component| {
+            let (key, value) = component.as_os_str().to_str()?.split_once('=')?;
+            let value =
+                (value != "__HIVE_DEFAULT_PARTITION__").then(|| decode_partition_value(value));
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+// decode the `%XX` escapes of a partition value, which the partitioned sinks write for the
+// characters that cannot be used in a path
+fn decode_partition_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn partition_columns(
+    paths: &[PathBuf],
+    start_idx: usize,
+    hive_options: &HiveOptions,
+) -> pl::PolarsResult<pl::DataFrame> {
+    let parsed: Vec<_> = paths
+        .iter()
+        .map(|path| parse_partitions(path, start_idx))
+        .collect();
+    let keys: Vec<String> = parsed
+        .first()
+        .map(|first| first.iter().map(|(key, _)| key.clone()).collect())
+        .unwrap_or_default();
+
+    let columns = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let values = parsed
+                .iter()
+                .zip(paths)
+                .map(|(partitions, path)| match partitions.get(i) {
+                    Some((k, value)) if k == key => Ok(value.clone()),
+                    _ => Err(pl::polars_err!(
+                        ComputeError: "hive partition key '{}' is missing in path '{}'",
+                        key, path.display()
+                    )),
+                })
+                .collect::<pl::PolarsResult<Vec<_>>>()?;
+
+            let dtype = match hive_options
+                .schema
+                .as_ref()
+                .and_then(|schema| schema.get(key.as_str()))
+            {
+                Some(dtype) => dtype.clone(),
+                None => {
+                    let possibilities: PlHashSet<pl::DataType> = values
+                        .iter()
+                        .flatten()
+                        .map(|v| infer_field_schema(v, hive_options.try_parse_dates, false))
+                        .collect();
+                    finish_infer_field_schema(&possibilities)
+                }
+            };
+            pl::Series::new(key.as_str().into(), values)
+                .strict_cast(&dtype)
+                .map(pl::Column::from)
+        })
+        .collect::<pl::PolarsResult<Vec<_>>>()?;
+
+    pl::DataFrame::new(columns)
+}
+
+/// Scan `paths` as a hive partitioned dataset if hive partitioning is enabled, or is `None`
+/// and a single directory is given. `scan_file` creates the native scan of a single file.
+/// Returns `None` if hive partitioning is not used, so the caller can use its native scan.
+#[allow(clippy::too_many_arguments)]
+pub fn scan_hive_partitioned(
+    paths: &[PathBuf],
+    glob: bool,
+    cloud_options: Option<&CloudOptions>,
+    hive_options: HiveOptions,
+    n_rows: Option<usize>,
+    row_index: Option<RowIndex>,
+    include_file_paths: Option<PlSmallStr>,
+    scan_file: impl Fn(&Path) -> pl::PolarsResult<pl::LazyFrame> + Send + Sync + 'static,
+) -> pl::PolarsResult<Option<pl::LazyFrame>> {
+    if hive_options.enabled == Some(false) {
+        return Ok(None);
+    }
+    let (expanded, start_idx) = expand_paths_hive(paths, glob, cloud_options, true)?;
+    if hive_options.enabled.is_none() && !expanded_from_single_directory(paths, expanded.as_ref()) {
+        return Ok(None);
+    }
+    let Some(first) = expanded.first() else {
+        pl::polars_bail!(ComputeError: "expected at least 1 source");
+    };
+
+    let partitions = partition_columns(expanded.as_ref(), start_idx, &hive_options)?;
+    let mut schema = (*scan_file(first)?.collect_schema()?).clone();
+    for column in partitions.get_columns() {
+        schema.with_column(column.name().clone(), column.dtype().clone());
+    }
+    if let Some(name) = &include_file_paths {
+        schema.with_column(name.clone(), pl::DataType::String);
+    }
+
+    let files = expanded
+        .iter()
+        .enumerate()
+        .map(|(i, path)| (path.clone(), partitions.slice(i as i64, 1)))
+        .collect();
+    scan_partitioned_files(
+        files,
+        Arc::new(schema),
+        n_rows,
+        row_index,
+        include_file_paths,
+        "HIVE SCAN",
+        scan_file,
+    )
+    .map(Some)
+}
+
+/// Scan `files` with their partition values, given as a DataFrame with one row per file.
+/// `schema` holds the columns of `scan_file`, followed by the partition columns and the column
+/// of `include_file_paths`.
+#[allow(clippy::too_many_arguments)]
+pub fn scan_partitioned_files(
+    files: Vec<(PathBuf, pl::DataFrame)>,
+    schema: pl::SchemaRef,
+    n_rows: Option<usize>,
+    row_index: Option<RowIndex>,
+    include_file_paths: Option<PlSmallStr>,
+    name: &'static str,
+    scan_file: impl Fn(&Path) -> pl::PolarsResult<pl::LazyFrame> + Send + Sync + 'static,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let scan = HiveScan {
+        files,
+        schema: schema.clone(),
+        include_file_paths,
+        scan_file: Box::new(scan_file),
+    };
+    let args = pl::ScanArgsAnonymous {
+        schema: Some(schema),
+        n_rows,
+        row_index,
+        name,
+        ..Default::default()
+    };
+    pl::LazyFrame::anonymous_scan(Arc::new(scan), args)
+}
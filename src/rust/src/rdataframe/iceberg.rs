@@ -0,0 +1,634 @@
+//scan Apache Iceberg tables
+
+use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::delta::{parse_decimal_type, read_file};
+use crate::rdataframe::hive_scan::scan_partitioned_files;
+use crate::rdatatype::robj_to_cloud_options;
+use crate::robj_to;
+use crate::rpolarserr::*;
+
+use extendr_api::{extendr, extendr_module, Robj};
+use polars::io::avro::AvroReader;
+use polars::io::cloud::CloudOptions;
+use polars::io::parquet::read::ParquetAsyncReader;
+use polars::io::path_utils::{expand_paths, is_cloud_url};
+use polars::io::{pl_async, HiveOptions, RowIndex};
+use polars::prelude::{self as pl, Arc, IntoLazy, PlSmallStr, SerReader};
+use polars_parquet::read::read_metadata;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+// An Iceberg table is described by metadata JSON files, each of which replaces the previous one.
+// The metadata has the schemas, the partition specs and the snapshots of the table. A snapshot
+// points to a manifest list, an Avro file of manifests, which are Avro files listing the data
+// files of the snapshot with their partition values. Columns are identified by field ids, so a
+// renamed column keeps its id.
+
+// the status of the manifest entries of files removed by the snapshot of the manifest
+const STATUS_DELETED: i32 = 2;
+
+// a top-level column of the table
+struct IcebergColumn {
+    id: i64,
+    name: PlSmallStr,
+    dtype: pl::DataType,
+}
+
+// a manifest of a snapshot, which lists data files (content 0) or delete files
+struct Manifest {
+    path: String,
+    spec_id: i64,
+    content: i32,
+}
+
+// the paths in the metadata are URIs, local files can have a `file:` scheme
+fn file_path(uri: &str) -> PathBuf {
+    let path = uri
+        .strip_prefix("file://")
+        .or_else(|| uri.strip_prefix("file:"))
+        .unwrap_or(uri);
+    PathBuf::from(path)
+}
+
+// `v<version>.metadata.json` or `<version>-<uuid>.metadata.json`
+fn metadata_version(name: &str) -> Option<u64> {
+    let name = name.strip_suffix(".metadata.json")?;
+    let name = name.strip_prefix('v').unwrap_or(name);
+    name.split('-').next()?.parse().ok()
+}
+
+/// Find the metadata file of the table at `source`: either a metadata file, or the directory of
+/// the table, of which the metadata file with the latest version is used.
+fn metadata_file(source: &str, cloud_options: Option<&CloudOptions>) -> pl::PolarsResult<PathBuf> {
+    if source.ends_with(".metadata.json") {
+        return Ok(file_path(source));
+    }
+    let table = source.trim_end_matches('/');
+    let pattern = file_path(&format!("{table}/metadata/*.metadata.json"));
+    let paths = expand_paths(&[pattern], true, cloud_options)?;
+    paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            Some((metadata_version(name)?, path))
+        })
+        .max_by_key(|(version, _)| *version)
+        .map(|(_, path)| path.clone())
+        .ok_or_else(|| pl::polars_err!(ComputeError: "no Iceberg table found at '{}'", source))
+}
+
+// read some columns of an Avro file, the columns missing in the file are skipped
+fn read_avro(bytes: Vec<u8>, columns: &[&str]) -> pl::PolarsResult<pl::DataFrame> {
+    let schema = AvroReader::new(Cursor::new(&bytes)).schema()?;
+    let columns = columns
+        .iter()
+        .filter(|name| schema.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+    AvroReader::new(Cursor::new(bytes))
+        .with_columns(Some(columns))
+        .finish()
+}
+
+// the top-level columns of a data file by their field id, empty if the file has no field ids.
+// Only the footer of the file is read, cloud files are not downloaded.
+fn parquet_field_ids(
+    path: &Path,
+    cloud_options: Option<&CloudOptions>,
+) -> pl::PolarsResult<HashMap<i64, PlSmallStr>> {
+    let metadata = if is_cloud_url(path) {
+        let uri = path.to_string_lossy();
+        pl_async::get_runtime().block_on_potential_spawn(async {
+            let mut reader = ParquetAsyncReader::from_uri(&uri, cloud_options, None).await?;
+            reader.get_metadata().await.cloned()
+        })?
+    } else {
+        read_metadata(&mut std::fs::File::open(path)?)?
+    };
+    Ok(metadata
+        .schema_descr
+        .fields()
+        .iter()
+        .filter_map(|field| {
+            let info = field.get_field_info();
+            Some((info.id? as i64, info.name.clone()))
+        })
+        .collect())
+}
+
+// the fields of an Iceberg struct type, with their field ids
+fn iceberg_fields(dtype: &Value) -> pl::PolarsResult<Vec<(i64, pl::Field)>> {
+    let fields = dtype
+        .get("fields")
+        .and_then(Value::as_array)
+        .ok_or_else(|| pl::polars_err!(ComputeError: "invalid Iceberg struct type: {}", dtype))?;
+    fields
+        .iter()
+        .map(|field| {
+            let (Some(id), Some(name)) = (
+                field.get("id").and_then(Value::as_i64),
+                field.get("name").and_then(Value::as_str),
+            ) else {
+                pl::polars_bail!(ComputeError: "invalid Iceberg schema field: {}", field);
+            };
+            let dtype = iceberg_to_polars_dtype(&field["type"])?;
+            Ok((id, pl::Field::new(name.into(), dtype)))
+        })
+        .collect()
+}
+
+fn iceberg_to_polars_dtype(dtype: &Value) -> pl::PolarsResult<pl::DataType> {
+    use pl::DataType as DT;
+    let field = |name: &str, key: &str| {
+        iceberg_to_polars_dtype(&dtype[key]).map(|dtype| pl::Field::new(name.into(), dtype))
+    };
+    Ok(match dtype {
+        Value::String(name) => match name.as_str() {
+            "boolean" => DT::Boolean,
+            "int" => DT::Int32,
+            "long" => DT::Int64,
+            "float" => DT::Float32,
+            "double" => DT::Float64,
+            "date" => DT::Date,
+            "time" => DT::Time,
+            "timestamp" => DT::Datetime(pl::TimeUnit::Microseconds, None),
+            "timestamptz" => DT::Datetime(pl::TimeUnit::Microseconds, Some("UTC".into())),
+            "timestamp_ns" => DT::Datetime(pl::TimeUnit::Nanoseconds, None),
+            "timestamptz_ns" => DT::Datetime(pl::TimeUnit::Nanoseconds, Some("UTC".into())),
+            "string" => DT::String,
+            "uuid" | "binary" => DT::Binary,
+            name if name.starts_with("fixed[") => DT::Binary,
+            name => parse_decimal_type(name).ok_or_else(
+                || pl::polars_err!(ComputeError: "the Iceberg type '{}' is not supported", name),
+            )?,
+        },
+        _ => match dtype.get("type").and_then(Value::as_str) {
+            Some("struct") => DT::Struct(
+                iceberg_fields(dtype)?
+                    .into_iter()
+                    .map(|(_, field)| field)
+                    .collect(),
+            ),
+            Some("list") => DT::List(Box::new(iceberg_to_polars_dtype(&dtype["element"])?)),
+            // a map is a list of key-value structs, like in parquet files
+            Some("map") => DT::List(Box::new(DT::Struct(vec![
+                field("key", "key")?,
+                field("value", "value")?,
+            ]))),
+            _ => pl::polars_bail!(ComputeError: "invalid Iceberg type: {}", dtype),
+        },
+    })
+}
+
+struct IcebergTable {
+    cloud_options: Option<CloudOptions>,
+    metadata: Value,
+}
+
+impl IcebergTable {
+    fn open(source: &str, cloud_options: Option<CloudOptions>) -> pl::PolarsResult<Self> {
+        let path = metadata_file(source, cloud_options.as_ref())?;
+        let bytes = read_file(&path, cloud_options.as_ref())?;
+        let metadata: Value = serde_json::from_slice(&bytes).map_err(
+            |err| pl::polars_err!(ComputeError: "invalid Iceberg metadata '{}': {}", path.display(), err),
+        )?;
+        let format_version = metadata.get("format-version").and_then(Value::as_u64);
+        if format_version.unwrap_or(1) > 2 {
+            pl::polars_bail!(
+                ComputeError: "version {} of the Iceberg table format is not supported",
+                format_version.unwrap_or_default()
+            );
+        }
+        Ok(IcebergTable {
+            cloud_options,
+            metadata,
+        })
+    }
+
+    fn read(&self, uri: &str) -> pl::PolarsResult<Vec<u8>> {
+        read_file(&file_path(uri), self.cloud_options.as_ref())
+    }
+
+    /// The snapshot `snapshot_id`, the current snapshot by default. Returns `None` for a table
+    /// without snapshots.
+    fn snapshot(&self, snapshot_id: Option<i64>) -> pl::PolarsResult<Option<&Value>> {
+        let current = self
+            .metadata
+            .get("current-snapshot-id")
+            .and_then(Value::as_i64)
+            .filter(|id| *id != -1);
+        let Some(id) = snapshot_id.or(current) else {
+            return Ok(None);
+        };
+        let snapshot = self
+            .metadata
+            .get("snapshots")
+            .and_then(Value::as_array)
+            .and_then(|snapshots| {
+                snapshots.iter().find(|snapshot| {
+                    snapshot.get("snapshot-id").and_then(Value::as_i64) == Some(id)
+                })
+            });
+        match snapshot {
+            Some(snapshot) => Ok(Some(snapshot)),
+            None => {
+                pl::polars_bail!(ComputeError: "snapshot {} of the Iceberg table does not exist", id)
+            }
+        }
+    }
+
+    // the first format version has a single schema
+    fn schemas(&self) -> Vec<&Value> {
+        match self.metadata.get("schemas").and_then(Value::as_array) {
+            Some(schemas) => schemas.iter().collect(),
+            None => self.metadata.get("schema").into_iter().collect(),
+        }
+    }
+
+    /// The columns of the schema of a snapshot, the current schema by default.
+    fn columns(&self, snapshot: Option<&Value>) -> pl::PolarsResult<Vec<IcebergColumn>> {
+        let id = snapshot
+            .and_then(|snapshot| snapshot.get("schema-id"))
+            .or_else(|| self.metadata.get("current-schema-id"));
+        let schemas = self.schemas();
+        let schema = match id {
+            Some(id) => schemas
+                .into_iter()
+                .find(|schema| schema.get("schema-id") == Some(id)),
+            None => schemas.into_iter().next(),
+        }
+        .ok_or_else(|| pl::polars_err!(ComputeError: "the Iceberg metadata has no schema"))?;
+        Ok(iceberg_fields(schema)?
+            .into_iter()
+            .map(|(id, field)| IcebergColumn {
+                id,
+                name: field.name,
+                dtype: field.dtype,
+            })
+            .collect())
+    }
+
+    // whether a column was renamed, or a name reused by another column, after which the columns
+    // of the data files must be matched by field id
+    fn has_renamed_columns(&self) -> bool {
+        let mut names = HashMap::new();
+        let mut ids = HashMap::new();
+        self.schemas()
+            .into_iter()
+            .filter_map(|schema| schema.get("fields").and_then(Value::as_array))
+            .flatten()
+            .filter_map(|field| {
+                Some((
+                    field.get("id").and_then(Value::as_i64)?,
+                    field.get("name").and_then(Value::as_str)?,
+                ))
+            })
+            .any(|(id, name)| {
+                *names.entry(id).or_insert(name) != name || *ids.entry(name).or_insert(id) != id
+            })
+    }
+
+    /// The identity partition fields of every partition spec, as the name of the partition
+    /// field by the field id of its source column.
+    fn identity_partitions(&self) -> HashMap<i64, HashMap<i64, String>> {
+        let identity_fields = |fields: &Value| -> HashMap<i64, String> {
+            fields
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|field| field.get("transform").and_then(Value::as_str) == Some("identity"))
+                .filter_map(|field| {
+                    Some((
+                        field.get("source-id").and_then(Value::as_i64)?,
+                        field.get("name").and_then(Value::as_str)?.to_string(),
+                    ))
+                })
+                .collect()
+        };
+        match self
+            .metadata
+            .get("partition-specs")
+            .and_then(Value::as_array)
+        {
+            Some(specs) => specs
+                .iter()
+                .filter_map(|spec| {
+                    let id = spec.get("spec-id").and_then(Value::as_i64)?;
+                    Some((id, identity_fields(spec.get("fields")?)))
+                })
+                .collect(),
+            // the first format version has a single spec
+            None => self
+                .metadata
+                .get("partition-spec")
+                .map(|fields| (0, identity_fields(fields)))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn manifests(&self, snapshot: &Value) -> pl::PolarsResult<Vec<Manifest>> {
+        let Some(list) = snapshot.get("manifest-list").and_then(Value::as_str) else {
+            // the first format version can list the manifests in the snapshot
+            let spec_id = self.metadata.get("default-spec-id").and_then(Value::as_i64);
+            return Ok(snapshot
+                .get("manifests")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(|path| Manifest {
+                    path: path.to_string(),
+                    spec_id: spec_id.unwrap_or(0),
+                    content: 0,
+                })
+                .collect());
+        };
+        let df = read_avro(
+            self.read(list)?,
+            &["manifest_path", "partition_spec_id", "content"],
+        )?;
+        let paths = df.column("manifest_path")?.str()?;
+        let spec_ids = df.column("partition_spec_id")?.cast(&pl::DataType::Int64)?;
+        // the first format version has no delete files
+        let contents: Vec<i32> = match df.column("content") {
+            Ok(content) => content
+                .cast(&pl::DataType::Int32)?
+                .i32()?
+                .iter()
+                .map(|content| content.unwrap_or(0))
+                .collect(),
+            Err(_) => vec![0; df.height()],
+        };
+        paths
+            .iter()
+            .zip(spec_ids.i64()?.iter())
+            .zip(contents)
+            .map(|((path, spec_id), content)| {
+                let path = path.ok_or_else(
+                    || pl::polars_err!(ComputeError: "invalid Iceberg manifest list '{}'", list),
+                )?;
+                Ok(Manifest {
+                    path: path.to_string(),
+                    spec_id: spec_id.unwrap_or(0),
+                    content,
+                })
+            })
+            .collect()
+    }
+
+    /// The live files of a manifest, with the values of the given identity partition columns.
+    fn manifest_files(
+        &self,
+        manifest: &Manifest,
+        partitions: &[(&IcebergColumn, &String)],
+    ) -> pl::PolarsResult<pl::DataFrame> {
+        let entries = read_avro(self.read(&manifest.path)?, &["status", "data_file"])?;
+        let Some(pl::DataType::Struct(fields)) = entries.schema().get("data_file").cloned() else {
+            pl::polars_bail!(ComputeError: "invalid Iceberg manifest '{}'", manifest.path);
+        };
+        let data_file = |name: &str| pl::col("data_file").struct_().field_by_name(name);
+        let mut exprs = vec![
+            data_file("file_path").alias("file_path"),
+            data_file("file_format").alias("file_format"),
+        ];
+        if fields.iter().any(|field| field.name() == "content") {
+            exprs.push(data_file("content").alias("content"));
+        }
+        for (column, field) in partitions {
+            exprs.push(
+                data_file("partition")
+                    .struct_()
+                    .field_by_name(field)
+                    .strict_cast(column.dtype.clone())
+                    .alias(column.name.clone()),
+            );
+        }
+        let files = entries
+            .lazy()
+            .filter(pl::col("status").neq(pl::lit(STATUS_DELETED)))
+            .select(exprs)
+            .collect()?;
+
+        let has_deletes = manifest.content != 0
+            || files
+                .column("content")
+                .ok()
+                .and_then(|content| content.cast(&pl::DataType::Int32).ok())
+                .is_some_and(|content| {
+                    content
+                        .i32()
+                        .is_ok_and(|content| content.iter().any(|x| x.unwrap_or(0) != 0))
+                });
+        if has_deletes && files.height() > 0 {
+            pl::polars_bail!(ComputeError: "Iceberg tables with delete files are not supported");
+        }
+        for format in files.column("file_format")?.str()?.iter() {
+            let format = format.unwrap_or_default();
+            if !format.eq_ignore_ascii_case("parquet") {
+                pl::polars_bail!(
+                    ComputeError: "Iceberg data files in the format '{}' are not supported, only Parquet files can be read",
+                    format
+                );
+            }
+        }
+        let mut names = vec![PlSmallStr::from("file_path")];
+        names.extend(partitions.iter().map(|(column, _)| column.name.clone()));
+        files.select(names)
+    }
+}
+
+// a data file is read with the types of the table, the columns that were added to the table
+// after the file was written are null
+fn scan_data_file(
+    path: &Path,
+    args: &pl::ScanArgsParquet,
+    columns: &[IcebergColumn],
+    match_by_id: bool,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let mut lf = pl::LazyFrame::scan_parquet(path, args.clone())?;
+    let available = lf.collect_schema()?;
+    let field_ids = if match_by_id {
+        parquet_field_ids(path, args.cloud_options.as_ref())?
+    } else {
+        HashMap::new()
+    };
+    let exprs = columns
+        .iter()
+        .map(|column| {
+            // files without field ids, e.g. of tables converted to Iceberg, are matched by name
+            let source = if field_ids.is_empty() {
+                Some(&column.name)
+            } else {
+                field_ids.get(&column.id)
+            };
+            match source.filter(|name| available.contains(name)) {
+                Some(name) => pl::col(name.clone())
+                    .strict_cast(column.dtype.clone())
+                    .alias(column.name.clone()),
+                None => pl::lit(pl::Null {})
+                    .cast(column.dtype.clone())
+                    .alias(column.name.clone()),
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(lf.select(exprs))
+}
+
+fn scan_iceberg(
+    source: &str,
+    snapshot_id: Option<i64>,
+    args: pl::ScanArgsParquet,
+    n_rows: Option<usize>,
+    row_index: Option<RowIndex>,
+    include_file_paths: Option<PlSmallStr>,
+) -> pl::PolarsResult<pl::LazyFrame> {
+    let table = IcebergTable::open(source, args.cloud_options.clone())?;
+    let snapshot = table.snapshot(snapshot_id)?;
+    let columns = table.columns(snapshot)?;
+    let names: Vec<PlSmallStr> = columns.iter().map(|column| column.name.clone()).collect();
+    let match_by_id = table.has_renamed_columns();
+    let manifests = match snapshot {
+        Some(snapshot) => table.manifests(snapshot)?,
+        None => Vec::new(),
+    };
+
+    // the values of the columns which are identity partitioned by all the partition specs of
+    // the files are known without reading the files, and skip the files of other partitions
+    let specs = table.identity_partitions();
+    let spec_ids: BTreeSet<i64> = manifests.iter().map(|manifest| manifest.spec_id).collect();
+    let (partition_columns, data_columns): (Vec<_>, Vec<_>) = columns.into_iter().partition(|c| {
+        !spec_ids.is_empty()
+            && spec_ids.iter().all(|id| {
+                specs
+                    .get(id)
+                    .is_some_and(|fields| fields.contains_key(&c.id))
+            })
+    });
+
+    let mut files: Option<pl::DataFrame> = None;
+    for manifest in &manifests {
+        let partitions: Vec<_> = partition_columns
+            .iter()
+            .map(|column| (column, &specs[&manifest.spec_id][&column.id]))
+            .collect();
+        let manifest_files = table.manifest_files(manifest, &partitions)?;
+        match files.as_mut() {
+            Some(files) => {
+                files.vstack_mut(&manifest_files)?;
+            }
+            None => files = Some(manifest_files),
+        }
+    }
+    let files = match files {
+        Some(files) => {
+            let paths = files.column("file_path")?.str()?.clone();
+            let partitions = files.drop("file_path")?;
+            paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    let path = file_path(path.unwrap_or_default());
+                    (path, partitions.slice(i as i64, 1))
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    // the partition columns are appended to the columns of the data files by the scan
+    let mut scan_schema: pl::Schema = data_columns
+        .iter()
+        .map(|column| (column.name.clone(), column.dtype.clone()))
+        .collect();
+    for column in &partition_columns {
+        scan_schema.with_column(column.name.clone(), column.dtype.clone());
+    }
+    if let Some(name) = &include_file_paths {
+        scan_schema.with_column(name.clone(), pl::DataType::String);
+    }
+    let order: Vec<pl::Expr> = row_index
+        .iter()
+        .map(|row_index| row_index.name.clone())
+        .chain(names)
+        .chain(include_file_paths.iter().cloned())
+        .map(pl::col)
+        .collect();
+
+    let lf = scan_partitioned_files(
+        files,
+        Arc::new(scan_schema),
+        n_rows,
+        row_index,
+        include_file_paths,
+        "ICEBERG SCAN",
+        move |path| scan_data_file(path, &args, &data_columns, match_by_id),
+    )?;
+    Ok(lf.select(order))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn new_from_iceberg(
+    path: Robj,
+    snapshot_id: Robj,
+    n_rows: Robj,
+    row_index_name: Robj,
+    row_index_offset: Robj,
+    parallel: Robj,
+    use_statistics: Robj,
+    low_memory: Robj,
+    storage_options: Robj,
+    include_file_paths: Robj,
+) -> RResult<RPolarsLazyFrame> {
+    let path = robj_to!(String, path)?;
+    let cloud_options = robj_to_cloud_options(&path, &storage_options)?;
+    // snapshot ids are 64-bit integers, which R numbers cannot represent exactly
+    let snapshot_id = match robj_to!(Option, String, snapshot_id)? {
+        Some(id) => match id.parse::<i64>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                return rerr()
+                    .bad_val(id)
+                    .misvalued("must be the id of a snapshot, as a string of digits")
+                    .bad_arg("snapshot_id")
+            }
+        },
+        None => None,
+    };
+    let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
+    let row_index = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+    // every data file is scanned on its own, with the partition values from the manifests
+    let args = pl::ScanArgsParquet {
+        parallel: robj_to!(ParallelStrategy, parallel)?,
+        use_statistics: robj_to!(bool, use_statistics)?,
+        low_memory: robj_to!(bool, low_memory)?,
+        cloud_options,
+        hive_options: HiveOptions {
+            enabled: Some(false),
+            ..Default::default()
+        },
+        glob: false,
+        ..Default::default()
+    };
+    scan_iceberg(
+        &path,
+        snapshot_id,
+        args,
+        robj_to!(Option, usize, n_rows)?,
+        row_index,
+        robj_to!(Option, String, include_file_paths)?.map(|x| x.into()),
+    )
+    .map_err(polars_to_rpolars_err)
+    .map(RPolarsLazyFrame)
+}
+
+extendr_module! {
+    mod iceberg;
+    fn new_from_iceberg;
+}
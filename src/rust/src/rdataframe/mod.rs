@@ -1,21 +1,33 @@
 use extendr_api::{extendr, prelude::*, rprintln};
 use polars::prelude::{self as pl, CompatLevel, IntoLazy, ParquetWriteOptions, SerWriter};
 use std::result::Result;
+pub mod compression;
+pub mod delta;
+pub mod hive_scan;
+pub mod iceberg;
+pub mod read_arrow_stream;
+pub mod read_avro;
 pub mod read_csv;
+pub mod read_fwf;
 pub mod read_ipc;
+pub mod read_json;
 pub mod read_ndjson;
 pub mod read_parquet;
+pub mod write_parquet;
+use crate::arrow_interop::field_metadata::{
+    self, columns_metadata_from_robj, fields_metadata_robj, ColumnsMetadata,
+};
 use crate::conversion_r_to_s::robjname2series;
 use crate::lazy;
 use crate::rdatatype;
-use crate::rdatatype::{new_parquet_compression, RPolarsDataType};
+use crate::rdatatype::{new_parquet_compression, robj_to_cloud_options, RPolarsDataType};
 use crate::robj_to;
 use crate::rpolarserr::*;
 use crate::utils::robj_to_usize;
 use either::Either;
 pub use lazy::dataframe::*;
 
-use crate::conversion_s_to_r::pl_series_to_list;
+use crate::conversion_s_to_r::{pl_series_to_list, ConversionOptions};
 pub use crate::series::*;
 
 use crate::utils::{collect_hinted_result, r_result_list};
@@ -90,6 +102,124 @@ impl From<pl::DataFrame> for RPolarsDataFrame {
     }
 }
 
+// open a local file or a cloud object for writing, cloud uploads are completed on drop
+pub(crate) fn create_writeable(
+    path: &str,
+    storage_options: &Robj,
+) -> RResult<Box<dyn std::io::Write + Send>> {
+    let cloud_options = robj_to_cloud_options(path, storage_options)?;
+    polars::io::utils::file::try_get_writeable(path, cloud_options.as_ref())
+        .map_err(polars_to_rpolars_err)
+}
+
+// write text output to `f`, compressed if `compression` is set, and return `f`
+pub(crate) fn write_text<W: std::io::Write>(
+    mut f: W,
+    compression: Option<compression::TextCompression>,
+    write: impl FnOnce(&mut dyn std::io::Write) -> RResult<()>,
+) -> RResult<W> {
+    let Some(compression) = compression else {
+        write(&mut f)?;
+        return Ok(f);
+    };
+    let io_err = |err: std::io::Error| polars_to_rpolars_err(err.into());
+    let mut encoder = compression.writer(f).map_err(io_err)?;
+    write(&mut encoder)?;
+    let mut f = encoder.finish().map_err(io_err)?;
+    f.flush().map_err(io_err)?;
+    Ok(f)
+}
+
+// shared by write_csv() and to_raw_csv(), so that they take the same options
+#[allow(clippy::too_many_arguments)]
+fn write_csv_frame<W: std::io::Write>(
+    f: W,
+    df: &pl::DataFrame,
+    include_bom: Robj,
+    include_header: Robj,
+    separator: Robj,
+    line_terminator: Robj,
+    quote_char: Robj,
+    batch_size: Robj,
+    datetime_format: Robj,
+    date_format: Robj,
+    time_format: Robj,
+    float_precision: Robj,
+    null_value: Robj,
+    quote_style: Robj,
+    compression: Robj,
+    compression_level: Robj,
+) -> RResult<W> {
+    let compression = rdatatype::new_text_compression(compression, compression_level)?;
+    write_text(f, compression, |f| {
+        pl::CsvWriter::new(f)
+            .include_bom(robj_to!(bool, include_bom)?)
+            .include_header(robj_to!(bool, include_header)?)
+            .with_separator(robj_to!(Utf8Byte, separator)?)
+            .with_line_terminator(robj_to!(String, line_terminator)?)
+            .with_quote_char(robj_to!(Utf8Byte, quote_char)?)
+            .with_batch_size(robj_to!(nonzero_usize, batch_size)?)
+            .with_datetime_format(robj_to!(Option, String, datetime_format)?)
+            .with_date_format(robj_to!(Option, String, date_format)?)
+            .with_time_format(robj_to!(Option, String, time_format)?)
+            .with_float_precision(robj_to!(Option, usize, float_precision)?)
+            .with_null_value(robj_to!(String, null_value)?)
+            .with_quote_style(robj_to!(QuoteStyle, quote_style)?)
+            .finish(&mut df.clone())
+            .map_err(polars_to_rpolars_err)
+    })
+}
+
+// shared by write_ndjson() and to_raw_ndjson(), so that they take the same options
+fn write_ndjson_frame<W: std::io::Write>(
+    f: W,
+    df: &pl::DataFrame,
+    compression: Robj,
+    compression_level: Robj,
+) -> RResult<W> {
+    let compression = rdatatype::new_text_compression(compression, compression_level)?;
+    write_text(f, compression, |f| {
+        pl::JsonWriter::new(f)
+            .with_json_format(pl::JsonFormat::JsonLines)
+            .finish(&mut df.clone())
+            .map_err(polars_to_rpolars_err)
+    })
+}
+
+// the native IPC writers only write the schema of the DataFrame, this writes the arrow fields of
+// the columns with their field metadata and extension types instead
+fn write_ipc_with_metadata<W: std::io::Write>(
+    writer: W,
+    df: &pl::DataFrame,
+    md: &ColumnsMetadata,
+    compression: Option<pl::IpcCompression>,
+    compat_level: CompatLevel,
+    stream: bool,
+) -> pl::PolarsResult<()> {
+    use arrow::io::ipc::write::{FileWriter, StreamWriter, WriteOptions};
+    let schema = field_metadata::df_arrow_schema(df, md, compat_level);
+    let options = WriteOptions {
+        compression: compression.map(|c| c.into()),
+    };
+    let mut df = df.clone();
+    df.align_chunks_par();
+    let batches = df.iter_chunks(compat_level, true);
+    if stream {
+        let mut writer = StreamWriter::new(writer, options);
+        writer.start(&schema, None)?;
+        for batch in batches {
+            writer.write(&batch, None)?;
+        }
+        writer.finish()
+    } else {
+        let mut writer = FileWriter::try_new(writer, std::sync::Arc::new(schema), None, options)?;
+        for batch in batches {
+            writer.write(&batch, None)?;
+        }
+        writer.finish()
+    }
+}
+
 #[extendr]
 impl RPolarsDataFrame {
     pub fn shape(&self) -> Robj {
@@ -235,13 +365,29 @@ impl RPolarsDataFrame {
         l
     }
 
-    pub fn to_list(&self, int64_conversion: &str) -> List {
-        let robj_vec_res: Result<Vec<Robj>, _> = collect_hinted_result(
-            self.0.width(),
-            self.0
-                .iter()
-                .map(|x| pl_series_to_list(x, false, int64_conversion)),
-        );
+    pub fn to_list(
+        &self,
+        int64_conversion: &str,
+        decimal_conversion: &str,
+        duration_conversion: &str,
+        time_conversion: &str,
+        array_conversion: &str,
+        struct_conversion: &str,
+    ) -> List {
+        let robj_vec_res: Result<Vec<Robj>, _> = ConversionOptions::new(
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        )
+        .and_then(|options| {
+            collect_hinted_result(
+                self.0.width(),
+                self.0.iter().map(|x| pl_series_to_list(x, false, &options)),
+            )
+        });
 
         let robj_list_res = robj_vec_res
             .map_err(|err| format!("conversion error for a polars Series to R: {}", err))
@@ -255,13 +401,29 @@ impl RPolarsDataFrame {
     }
 
     //this methods should only be used for benchmarking
-    pub fn to_list_unwind(&self, int64_conversion: &str) -> Robj {
-        let robj_vec_res: Result<Vec<Robj>, _> = collect_hinted_result(
-            self.0.width(),
-            self.0
-                .iter()
-                .map(|x| pl_series_to_list(x, false, int64_conversion)),
-        );
+    pub fn to_list_unwind(
+        &self,
+        int64_conversion: &str,
+        decimal_conversion: &str,
+        duration_conversion: &str,
+        time_conversion: &str,
+        array_conversion: &str,
+        struct_conversion: &str,
+    ) -> Robj {
+        let robj_vec_res: Result<Vec<Robj>, _> = ConversionOptions::new(
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        )
+        .and_then(|options| {
+            collect_hinted_result(
+                self.0.width(),
+                self.0.iter().map(|x| pl_series_to_list(x, false, &options)),
+            )
+        });
 
         let robj_list_res = robj_vec_res
             .map_err(|err| format!("conversion error for a polars Series to R: {}", err))
@@ -276,14 +438,30 @@ impl RPolarsDataFrame {
 
     // to_list have this variant with set_structs = true at pl_series_to_list
     // does not expose this arg in to_list as it is quite niche and might be deprecated later
-    pub fn to_list_tag_structs(&self, int64_conversion: &str) -> List {
+    pub fn to_list_tag_structs(
+        &self,
+        int64_conversion: &str,
+        decimal_conversion: &str,
+        duration_conversion: &str,
+        time_conversion: &str,
+        array_conversion: &str,
+        struct_conversion: &str,
+    ) -> List {
         //convert DataFrame to Result of to R vectors, error if DataType is not supported
-        let robj_vec_res: Result<Vec<Robj>, _> = collect_hinted_result(
-            self.0.width(),
-            self.0
-                .iter()
-                .map(|x| pl_series_to_list(x, true, int64_conversion)),
-        );
+        let robj_vec_res: Result<Vec<Robj>, _> = ConversionOptions::new(
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        )
+        .and_then(|options| {
+            collect_hinted_result(
+                self.0.width(),
+                self.0.iter().map(|x| pl_series_to_list(x, true, &options)),
+            )
+        });
 
         //rewrap Ok(Vec<Robj>) as R list
         let robj_list_res = robj_vec_res
@@ -362,9 +540,15 @@ impl RPolarsDataFrame {
         Ok(List::from_values(vec))
     }
 
-    pub fn export_stream(&self, stream_ptr: &str, compat_level: Robj) {
-        let compat_level = robj_to!(CompatLevel, compat_level).unwrap();
-        let schema = self.0.schema().to_arrow(compat_level);
+    pub fn export_stream(
+        &self,
+        stream_ptr: &str,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let schema = field_metadata::df_arrow_schema(&self.0, &md, compat_level);
         // TODO: changed when bumping to 0.43.1, might need refactor
         let data_type = ArrowDataType::Struct(schema.iter_values().map(|x| x.clone()).collect());
         let field = ArrowField::new("".into(), data_type, false);
@@ -380,12 +564,72 @@ impl RPolarsDataFrame {
                 1,
             );
         }
+        Ok(())
+    }
+
+    pub fn import_stream(stream_ptr: Robj) -> RResult<Robj> {
+        use crate::arrow_interop::to_rust::{
+            array_stream_fields, array_stream_to_df, import_array_stream,
+        };
+        let stream_in_ptr_addr = robj_to!(usize, stream_ptr)?;
+        let mut stream =
+            unsafe { import_array_stream(stream_in_ptr_addr) }.map_err(polars_to_rpolars_err)?;
+        let fields = array_stream_fields(&stream).map_err(polars_to_rpolars_err)?;
+        let projection: Vec<usize> = (0..fields.len()).collect();
+        let df = array_stream_to_df(&mut stream, &fields, &projection, None)
+            .map_err(polars_to_rpolars_err)?;
+        field_metadata::set_attr(RPolarsDataFrame(df).into(), fields_metadata_robj(&fields))
+    }
+
+    pub fn export_array(
+        &self,
+        array_ptr: Robj,
+        schema_ptr: Robj,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        use crate::arrow_interop::{export_array_to_c, robj_to_ptr_addr};
+        use pl::IntoSeries;
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let array_addr = robj_to_ptr_addr(array_ptr)?;
+        let schema_addr = robj_to_ptr_addr(schema_ptr)?;
+        // a DataFrame is exported as a struct array, like a record batch
+        let s = self
+            .0
+            .clone()
+            .into_struct("".into())
+            .into_series()
+            .rechunk();
+        let schema = field_metadata::df_arrow_schema(&self.0, &md, compat_level);
+        let data_type = ArrowDataType::Struct(schema.iter_values().cloned().collect());
+        let field = ArrowField::new("".into(), data_type, false);
+        unsafe { export_array_to_c(s.to_arrow(0, compat_level), &field, array_addr, schema_addr) };
+        Ok(())
     }
 
-    pub fn from_arrow_record_batches(rbr: Robj) -> Result<RPolarsDataFrame, String> {
-        Ok(RPolarsDataFrame(unsafe {
-            crate::arrow_interop::to_rust::to_rust_df(rbr)
-        }?))
+    pub fn import_array(array_ptr: Robj, schema_ptr: Robj) -> RResult<Robj> {
+        use crate::arrow_interop::to_rust::struct_array_to_df;
+        use crate::arrow_interop::{import_array_from_c, robj_to_ptr_addr};
+        let array_addr = robj_to_ptr_addr(array_ptr)?;
+        let schema_addr = robj_to_ptr_addr(schema_ptr)?;
+        let (field, array) = unsafe { import_array_from_c(array_addr, schema_addr) }
+            .map_err(polars_to_rpolars_err)?;
+        let (df, md) = struct_array_to_df(&field, array).map_err(polars_to_rpolars_err)?;
+        field_metadata::set_attr(RPolarsDataFrame(df).into(), md)
+    }
+
+    // the names of the columns of `self` that have a column with the same name and dtype in
+    // `other`, to pass the field metadata of `other` on to `self`
+    pub fn shared_columns(&self, other: &RPolarsDataFrame) -> RResult<Vec<String>> {
+        let other_schema = other.0.schema();
+        Ok(self
+            .0
+            .get_columns()
+            .iter()
+            .filter(|c| other_schema.get(c.name()) == Some(c.dtype()))
+            .map(|c| c.name().to_string())
+            .collect())
     }
 
     pub fn estimated_size(&self) -> f64 {
@@ -538,43 +782,155 @@ impl RPolarsDataFrame {
         float_precision: Robj,
         null_value: Robj,
         quote_style: Robj,
+        compression: Robj,
+        compression_level: Robj,
+        storage_options: Robj,
     ) -> RResult<()> {
-        let file = robj_to!(str, file)?;
-        let f = std::fs::File::create(file)?;
-        pl::CsvWriter::new(f)
-            .include_bom(robj_to!(bool, include_bom)?)
-            .include_header(robj_to!(bool, include_header)?)
-            .with_separator(robj_to!(Utf8Byte, separator)?)
-            .with_line_terminator(robj_to!(String, line_terminator)?)
-            .with_quote_char(robj_to!(Utf8Byte, quote_char)?)
-            .with_batch_size(robj_to!(nonzero_usize, batch_size)?)
-            .with_datetime_format(robj_to!(Option, String, datetime_format)?)
-            .with_date_format(robj_to!(Option, String, date_format)?)
-            .with_time_format(robj_to!(Option, String, time_format)?)
-            .with_float_precision(robj_to!(Option, usize, float_precision)?)
-            .with_null_value(robj_to!(String, null_value)?)
-            .with_quote_style(robj_to!(QuoteStyle, quote_style)?)
+        let f = create_writeable(robj_to!(str, file)?, &storage_options)?;
+        write_csv_frame(
+            f,
+            &self.0,
+            include_bom,
+            include_header,
+            separator,
+            line_terminator,
+            quote_char,
+            batch_size,
+            datetime_format,
+            date_format,
+            time_format,
+            float_precision,
+            null_value,
+            quote_style,
+            compression,
+            compression_level,
+        )?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn to_raw_csv(
+        &self,
+        include_bom: Robj,
+        include_header: Robj,
+        separator: Robj,
+        line_terminator: Robj,
+        quote_char: Robj,
+        batch_size: Robj,
+        datetime_format: Robj,
+        date_format: Robj,
+        time_format: Robj,
+        float_precision: Robj,
+        null_value: Robj,
+        quote_style: Robj,
+        compression: Robj,
+        compression_level: Robj,
+    ) -> RResult<Vec<u8>> {
+        write_csv_frame(
+            Vec::new(),
+            &self.0,
+            include_bom,
+            include_header,
+            separator,
+            line_terminator,
+            quote_char,
+            batch_size,
+            datetime_format,
+            date_format,
+            time_format,
+            float_precision,
+            null_value,
+            quote_style,
+            compression,
+            compression_level,
+        )
+    }
+
+    pub fn write_ipc(
+        &self,
+        file: Robj,
+        compression: Robj,
+        compat_level: Robj,
+        storage_options: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        let compression = rdatatype::new_ipc_compression(compression)?;
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let file = create_writeable(robj_to!(str, file)?, &storage_options)?;
+        if !md.is_empty() {
+            return write_ipc_with_metadata(file, &self.0, &md, compression, compat_level, false)
+                .map_err(polars_to_rpolars_err);
+        }
+        pl::IpcWriter::new(file)
+            .with_compression(compression)
+            .with_compat_level(compat_level)
             .finish(&mut self.0.clone())
             .map_err(polars_to_rpolars_err)
     }
 
-    pub fn write_ipc(&self, file: Robj, compression: Robj, compat_level: Robj) -> RResult<()> {
-        let file = std::fs::File::create(robj_to!(str, file)?)?;
-        pl::IpcWriter::new(file)
-            .with_compression(rdatatype::new_ipc_compression(compression)?)
-            .with_compat_level(robj_to!(CompatLevel, compat_level)?)
+    pub fn write_ipc_stream(
+        &self,
+        file: Robj,
+        compression: Robj,
+        compat_level: Robj,
+        storage_options: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        let compression = rdatatype::new_ipc_compression(compression)?;
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let file = create_writeable(robj_to!(str, file)?, &storage_options)?;
+        if !md.is_empty() {
+            return write_ipc_with_metadata(file, &self.0, &md, compression, compat_level, true)
+                .map_err(polars_to_rpolars_err);
+        }
+        pl::IpcStreamWriter::new(file)
+            .with_compression(compression)
+            .with_compat_level(compat_level)
             .finish(&mut self.0.clone())
             .map_err(polars_to_rpolars_err)
     }
 
-    pub fn to_raw_ipc(&self, compression: Robj, compat_level: Robj) -> RResult<Vec<u8>> {
+    pub fn to_raw_ipc(
+        &self,
+        compression: Robj,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<Vec<u8>> {
         let compression = rdatatype::new_ipc_compression(compression)?;
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        if !md.is_empty() {
+            let mut buf = Vec::new();
+            write_ipc_with_metadata(&mut buf, &self.0, &md, compression, compat_level, false)
+                .map_err(polars_to_rpolars_err)?;
+            return Ok(buf);
+        }
+        crate::rbackground::serialize_dataframe(&mut self.0.clone(), compression, compat_level)
+    }
 
-        crate::rbackground::serialize_dataframe(
-            &mut self.0.clone(),
-            compression,
-            robj_to!(CompatLevel, compat_level)?,
-        )
+    pub fn to_raw_ipc_stream(
+        &self,
+        compression: Robj,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<Vec<u8>> {
+        let compression = rdatatype::new_ipc_compression(compression)?;
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let mut buf = Vec::new();
+        if !md.is_empty() {
+            write_ipc_with_metadata(&mut buf, &self.0, &md, compression, compat_level, true)
+                .map_err(polars_to_rpolars_err)?;
+            return Ok(buf);
+        }
+        pl::IpcStreamWriter::new(&mut buf)
+            .with_compression(compression)
+            .with_compat_level(compat_level)
+            .finish(&mut self.0.clone())
+            .map_err(polars_to_rpolars_err)?;
+        Ok(buf)
     }
 
     pub fn from_raw_ipc(
@@ -606,8 +962,11 @@ impl RPolarsDataFrame {
         statistics: Robj,
         row_group_size: Robj,
         data_page_size: Robj,
+        extra_options: Robj,
         partition_by: Robj,
         partition_chunk_size_bytes: Robj,
+        storage_options: Robj,
+        field_metadata: Robj,
     ) -> RResult<()> {
         use polars::prelude::write_partitioned_dataset;
         let file = robj_to!(str, file)?;
@@ -615,21 +974,32 @@ impl RPolarsDataFrame {
         let statistics = robj_to!(StatisticsOptions, statistics)?;
         let row_group_size = robj_to!(Option, usize, row_group_size)?;
         let data_page_size = robj_to!(Option, usize, data_page_size)?;
+        let extra_options = robj_to!(ParquetExtraOptions, extra_options)?;
         let partition_by = robj_to!(Option, Vec, String, partition_by)?;
         let partition_chunk_size_bytes = robj_to_usize(partition_chunk_size_bytes)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let write_options = ParquetWriteOptions {
+            compression,
+            statistics,
+            row_group_size,
+            data_page_size,
+            maintain_order: true,
+        };
 
         if let Some(partition_by) = partition_by {
+            if !storage_options.is_null() {
+                return rerr()
+                    .bad_arg("storage_options")
+                    .plain("is not supported together with `partition_by`");
+            }
+            if !extra_options.is_empty() {
+                return rerr()
+                    .bad_arg("partition_by")
+                    .plain("is not supported together with key-value metadata, column options or sorting columns");
+            }
             let data = &mut self.0.clone();
             let path = file;
 
-            let write_options = ParquetWriteOptions {
-                compression,
-                statistics,
-                row_group_size,
-                data_page_size,
-                maintain_order: true,
-            };
-
             let out = write_partitioned_dataset(
                 data,
                 std::path::Path::new(path),
@@ -642,23 +1012,60 @@ impl RPolarsDataFrame {
             return Ok(out);
         };
 
-        let f = std::fs::File::create(file)?;
-        let out = pl::ParquetWriter::new(f)
-            .with_compression(compression)
-            .with_statistics(statistics)
-            .with_row_group_size(row_group_size)
-            .with_data_page_size(data_page_size)
-            .set_parallel(true)
-            .finish(&mut self.0.clone())
+        let f = create_writeable(file, &storage_options)?;
+        write_parquet::write_frame(f, &self.0, &md, &write_options, &extra_options)
             .map_err(polars_to_rpolars_err)
-            // Ignore the u64 returned by .finish()
-            .map(|_| ())?;
+    }
 
-        Ok(out.into())
+    pub fn write_delta(
+        &self,
+        target: Robj,
+        mode: Robj,
+        partition_by: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        let target = robj_to!(String, target)?;
+        let cloud_options = robj_to_cloud_options(&target, &storage_options)?;
+        let mode = delta::robj_to_delta_write_mode(mode)?;
+        let partition_by = robj_to!(Option, Vec, String, partition_by)?;
+        delta::write_delta(&self.0, &target, mode, partition_by, cloud_options)
+            .map_err(polars_to_rpolars_err)
     }
 
-    pub fn write_json(&mut self, file: Robj, pretty: Robj, row_oriented: Robj) -> RResult<()> {
-        let f = std::fs::File::create(robj_to!(str, file)?)?;
+    #[allow(clippy::too_many_arguments)]
+    pub fn to_raw_parquet(
+        &self,
+        compression_method: Robj,
+        compression_level: Robj,
+        statistics: Robj,
+        row_group_size: Robj,
+        data_page_size: Robj,
+        extra_options: Robj,
+        field_metadata: Robj,
+    ) -> RResult<Vec<u8>> {
+        let write_options = ParquetWriteOptions {
+            compression: new_parquet_compression(compression_method, compression_level)?,
+            statistics: robj_to!(StatisticsOptions, statistics)?,
+            row_group_size: robj_to!(Option, usize, row_group_size)?,
+            data_page_size: robj_to!(Option, usize, data_page_size)?,
+            maintain_order: true,
+        };
+        let extra_options = robj_to!(ParquetExtraOptions, extra_options)?;
+        let md = columns_metadata_from_robj(field_metadata)?;
+        let mut buf = Vec::new();
+        write_parquet::write_frame(&mut buf, &self.0, &md, &write_options, &extra_options)
+            .map_err(polars_to_rpolars_err)?;
+        Ok(buf)
+    }
+
+    pub fn write_json(
+        &mut self,
+        file: Robj,
+        pretty: Robj,
+        row_oriented: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        let f = create_writeable(robj_to!(str, file)?, &storage_options)?;
         match (robj_to!(bool, pretty)?, robj_to!(bool, row_oriented)?) {
             (_, true) => pl::JsonWriter::new(f)
                 .with_json_format(pl::JsonFormat::Json)
@@ -672,22 +1079,46 @@ impl RPolarsDataFrame {
         .map_err(polars_to_rpolars_err)
     }
 
-    pub fn write_ndjson(&mut self, file: Robj) -> RResult<()> {
-        let f = std::fs::File::create(robj_to!(str, file)?)?;
-        pl::JsonWriter::new(f)
-            .with_json_format(pl::JsonFormat::JsonLines)
+    pub fn write_ndjson(
+        &mut self,
+        file: Robj,
+        compression: Robj,
+        compression_level: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        let f = create_writeable(robj_to!(str, file)?, &storage_options)?;
+        write_ndjson_frame(f, &self.0, compression, compression_level)?;
+        Ok(())
+    }
+
+    pub fn to_raw_ndjson(&self, compression: Robj, compression_level: Robj) -> RResult<Vec<u8>> {
+        write_ndjson_frame(Vec::new(), &self.0, compression, compression_level)
+    }
+
+    pub fn write_avro(
+        &mut self,
+        file: Robj,
+        compression: Robj,
+        name: Robj,
+        storage_options: Robj,
+    ) -> RResult<()> {
+        use polars::io::avro::AvroWriter;
+        let f = create_writeable(robj_to!(str, file)?, &storage_options)?;
+        AvroWriter::new(f)
+            .with_compression(rdatatype::new_avro_compression(compression)?)
+            .with_name(robj_to!(String, name)?)
             .finish(&mut self.0)
             .map_err(polars_to_rpolars_err)
     }
 }
 
 impl RPolarsDataFrame {
-    pub fn to_list_result(&self, int64_conversion: &str) -> Result<Robj, pl::PolarsError> {
+    pub fn to_list_result(&self, options: &ConversionOptions) -> Result<Robj, pl::PolarsError> {
         //convert DataFrame to Result of to R vectors, error if DataType is not supported
         let robj_vec_res: Result<Vec<Robj>, _> = self
             .0
             .iter()
-            .map(|s| pl_series_to_list(s, true, int64_conversion))
+            .map(|s| pl_series_to_list(s, true, options))
             .collect();
 
         //rewrap Ok(Vec<Robj>) as R list
@@ -719,8 +1150,14 @@ impl RPolarsVecDataFrame {
 
 extendr_module! {
     mod rdataframe;
+    use delta;
+    use iceberg;
+    use read_arrow_stream;
+    use read_avro;
     use read_csv;
+    use read_fwf;
     use read_ipc;
+    use read_json;
     use read_ndjson;
     use read_parquet;
     use rdatatype;
@@ -0,0 +1,167 @@
+use crate::arrow_interop::field_metadata::dtype_from_arrow_field;
+use crate::arrow_interop::to_rust::{
+    array_stream_fields, array_stream_to_df, import_array_stream, ArrayStreamReader,
+};
+use crate::concurrent::RFnSignature;
+use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::robj_to;
+use crate::rpolarserr::{polars_to_rpolars_err, rerr, RResult, WithRctx};
+use crate::utils::extendr_concurrent::{ParRObj, ThreadCom};
+use crate::CONFIG;
+
+use extendr_api::{extendr, extendr_module, ExternalPtr, Robj};
+use polars::prelude::{self as pl, Arc, ArrowField};
+use polars_plan::plans::{ArenaLpIter, FileScan, IR};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The producer of a stream may be implemented in R, e.g. by nanoarrow, so the stream is kept in
+// an external pointer and it is only accessed, and released, in the R main thread.
+struct ArrowStream {
+    reader: RefCell<Option<ArrayStreamReader>>,
+    fields: Vec<ArrowField>,
+}
+
+impl std::fmt::Debug for ArrowStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ArrowStream({} fields)", self.fields.len())
+    }
+}
+
+/// Pull the record batches of the stream in `stream`, with the columns of `projection` and up
+/// to `n_rows` rows. This must be called in the R main thread, and the stream is consumed.
+pub fn pull_batches(
+    stream: &Robj,
+    projection: &[usize],
+    n_rows: Option<usize>,
+) -> pl::PolarsResult<pl::DataFrame> {
+    let stream: ExternalPtr<ArrowStream> = stream
+        .clone()
+        .try_into()
+        .map_err(|err: extendr_api::Error| pl::polars_err!(ComputeError: "{}", err))?;
+    let Some(mut reader) = stream.reader.borrow_mut().take() else {
+        pl::polars_bail!(
+            ComputeError: "the arrow stream was already consumed, \
+            a LazyFrame of a stream can only be collected once"
+        );
+    };
+    array_stream_to_df(&mut reader, &stream.fields, projection, n_rows)
+}
+
+// record batches are pulled from the stream only when the query is collected, the pushed down
+// projection skips the conversion of unused columns and the slice stops pulling batches early.
+// A stream can only be consumed once, so the LazyFrame can only be collected once.
+struct ArrowStreamScan {
+    stream: ParRObj,
+    schema: pl::SchemaRef,
+    consumed: AtomicBool,
+}
+
+/// Raise an error if the query of `lf` scans an arrow stream. The batches of a stream are pulled
+/// in the R main thread, so it cannot be read by `method`, which runs the query without it.
+pub fn check_no_arrow_stream(lf: &pl::LazyFrame, method: &str) -> RResult<()> {
+    let plan = lf.clone().to_alp().map_err(polars_to_rpolars_err)?;
+    let scans_stream = (&plan.lp_arena).iter(plan.lp_top).any(|(_, lp)| match lp {
+        IR::Scan {
+            scan_type: FileScan::Anonymous { function, .. },
+            ..
+        } => function.as_any().is::<ArrowStreamScan>(),
+        _ => false,
+    });
+    if scans_stream {
+        return rerr().plain(format!(
+            "a LazyFrame of an arrow stream cannot be read by `{method}`, \
+            only by `$collect()`, `$fetch()` or `$profile()`"
+        ));
+    }
+    Ok(())
+}
+
+impl pl::AnonymousScan for ArrowStreamScan {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: pl::AnonymousScanArgs) -> pl::PolarsResult<pl::DataFrame> {
+        let projection: Vec<usize> = match scan_opts.with_columns {
+            Some(columns) => {
+                let mut idx: Vec<usize> = columns
+                    .iter()
+                    .filter_map(|name| self.schema.index_of(name))
+                    .collect();
+                idx.sort_unstable();
+                idx
+            }
+            None => (0..self.schema.len()).collect(),
+        };
+        if self.consumed.swap(true, Ordering::SeqCst) {
+            pl::polars_bail!(
+                ComputeError: "the arrow stream was already consumed, \
+                a LazyFrame of a stream can only be collected once"
+            );
+        }
+        // the polars threads of `$collect()`, `$fetch()` and `$profile()` can request the R
+        // main thread to pull the batches
+        let thread_com = ThreadCom::try_from_global(&CONFIG).map_err(|_| {
+            pl::polars_err!(
+                ComputeError: "a LazyFrame of an arrow stream can only be read by \
+                `$collect()`, `$fetch()` or `$profile()`"
+            )
+        })?;
+        thread_com.send(RFnSignature::ArrowStreamToDataFrame(
+            self.stream.clone(),
+            projection,
+            scan_opts.n_rows,
+        ));
+        thread_com.recv().unwrap_dataframe()
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> pl::PolarsResult<pl::SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+#[extendr]
+pub fn new_from_arrow_stream(stream_ptr: Robj) -> RResult<RPolarsLazyFrame> {
+    let stream_in_ptr_addr = robj_to!(usize, stream_ptr)?;
+    let stream =
+        unsafe { import_array_stream(stream_in_ptr_addr) }.map_err(polars_to_rpolars_err)?;
+    let fields = array_stream_fields(&stream).map_err(polars_to_rpolars_err)?;
+    let mut schema = pl::Schema::with_capacity(fields.len());
+    for field in fields.iter() {
+        schema.with_column(field.name.clone(), dtype_from_arrow_field(field));
+    }
+    let schema = Arc::new(schema);
+
+    let stream = ArrowStream {
+        reader: RefCell::new(Some(stream)),
+        fields,
+    };
+    let scan = ArrowStreamScan {
+        stream: ParRObj(ExternalPtr::new(stream).into()),
+        schema: schema.clone(),
+        consumed: AtomicBool::new(false),
+    };
+    let args = pl::ScanArgsAnonymous {
+        schema: Some(schema),
+        name: "ARROW STREAM SCAN",
+        ..Default::default()
+    };
+
+    pl::LazyFrame::anonymous_scan(Arc::new(scan), args)
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsLazyFrame)
+}
+
+extendr_module! {
+    mod read_arrow_stream;
+    fn new_from_arrow_stream;
+}
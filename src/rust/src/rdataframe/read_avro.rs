@@ -0,0 +1,109 @@
+use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::delta::read_file;
+use crate::rdatatype::robj_to_cloud_options;
+use crate::robj_to;
+use crate::rpolarserr::{polars_to_rpolars_err, RResult};
+
+use either::Either;
+use extendr_api::{extendr, extendr_module, Robj};
+use polars::io::avro::AvroReader;
+use polars::io::cloud::CloudOptions;
+use polars::io::path_utils::is_cloud_url;
+use polars::io::RowIndex;
+use polars::prelude::{self as pl, Arc, SerReader};
+use std::fs::File;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+// polars has no native avro scan node, so the file is wrapped in an anonymous scan
+// which receives the pushed down projection and slice from the optimizer
+struct AvroScan {
+    path: PathBuf,
+    cloud_options: Option<CloudOptions>,
+    rechunk: bool,
+}
+
+impl AvroScan {
+    // the avro reader needs to seek, so cloud files are downloaded into memory
+    fn open(&self) -> pl::PolarsResult<Either<File, Cursor<Vec<u8>>>> {
+        if is_cloud_url(&self.path) {
+            let bytes = read_file(&self.path, self.cloud_options.as_ref())?;
+            Ok(Either::Right(Cursor::new(bytes)))
+        } else {
+            Ok(Either::Left(File::open(&self.path)?))
+        }
+    }
+}
+
+impl pl::AnonymousScan for AvroScan {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: pl::AnonymousScanArgs) -> pl::PolarsResult<pl::DataFrame> {
+        // read projected columns in file order, the optimizer reorders them afterwards
+        let projection = scan_opts.with_columns.map(|columns| {
+            let mut idx: Vec<usize> = columns
+                .iter()
+                .filter_map(|name| scan_opts.schema.index_of(name))
+                .collect();
+            idx.sort_unstable();
+            idx
+        });
+        AvroReader::new(self.open()?)
+            .with_projection(projection)
+            .with_n_rows(scan_opts.n_rows)
+            .set_rechunk(self.rechunk)
+            .finish()
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> pl::PolarsResult<pl::SchemaRef> {
+        Ok(Arc::new(AvroReader::new(self.open()?).schema()?))
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+#[extendr]
+pub fn new_from_avro(
+    path: Robj,
+    n_rows: Robj,
+    rechunk: Robj,
+    row_name: Robj,
+    row_index: Robj,
+    storage_options: Robj,
+) -> RResult<RPolarsLazyFrame> {
+    let offset = robj_to!(Option, u32, row_index)?.unwrap_or(0);
+    let opt_row_index = robj_to!(Option, String, row_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+    let path = robj_to!(PathBuf, path)?;
+    let cloud_options = robj_to_cloud_options(&path.to_string_lossy(), &storage_options)?;
+    let scan = AvroScan {
+        path,
+        cloud_options,
+        rechunk: robj_to!(bool, rechunk)?,
+    };
+    let args = pl::ScanArgsAnonymous {
+        n_rows: robj_to!(Option, usize, n_rows)?,
+        row_index: opt_row_index,
+        name: "AVRO SCAN",
+        ..Default::default()
+    };
+
+    pl::LazyFrame::anonymous_scan(Arc::new(scan), args)
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsLazyFrame)
+}
+
+extendr_module! {
+    mod read_avro;
+    fn new_from_avro;
+}
@@ -1,11 +1,14 @@
 //read csv
 
-use crate::rdatatype::RPolarsDataTypeVector;
+use crate::rdatatype::{robj_to_cloud_options, schema_to_pairlist, RPolarsDataTypeVector};
 
 use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::compression::{scan_decompressed, scan_file, TextReader};
+use crate::rdataframe::hive_scan::scan_hive_partitioned;
+use crate::rdataframe::RPolarsDataFrame;
 use crate::robj_to;
 use crate::rpolarserr::*;
-use polars::io::RowIndex;
+use polars::io::{HiveOptions, RowIndex};
 
 //use crate::utils::wrappers::*;
 use crate::utils::wrappers::{null_to_opt, Wrap};
@@ -17,7 +20,7 @@ use std::result::Result;
 #[derive(Clone, Debug)]
 pub struct RPolarsRNullValues(pl::NullValues);
 
-use polars::prelude::LazyFileListReader;
+use polars::prelude::{LazyFileListReader, SerReader};
 
 #[extendr]
 impl RPolarsRNullValues {
@@ -57,7 +60,7 @@ pub fn new_from_csv(
     skip_rows: Robj,
     dtypes: Nullable<&RPolarsDataTypeVector>,
     null_values: Nullable<&RPolarsRNullValues>,
-    // missing_utf8_is_empty_string: Robj,
+    missing_is_null: Robj,
     ignore_errors: Robj,
     cache: Robj,
     infer_schema_length: Robj,
@@ -73,6 +76,13 @@ pub fn new_from_csv(
     raise_if_empty: Robj,
     truncate_ragged_lines: Robj,
     include_file_paths: Robj,
+    storage_options: Robj,
+    hive_partitioning: Robj,
+    hive_schema: Robj,
+    try_parse_hive_dates: Robj,
+    glob: Robj,
+    schema: Robj,
+    new_columns: Robj,
 ) -> RResult<RPolarsLazyFrame> {
     let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
     let opt_rowcount = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
@@ -81,61 +91,562 @@ pub fn new_from_csv(
     });
 
     let vec_pathbuf = robj_to!(Vec, PathBuf, path)?;
+    let cloud_options = vec_pathbuf
+        .first()
+        .map(|first| robj_to_cloud_options(&first.to_string_lossy(), &storage_options))
+        .transpose()?
+        .flatten();
     let linereader = match vec_pathbuf.len() {
-        2.. => Ok(pl::LazyCsvReader::new_paths(vec_pathbuf.into())),
+        2.. => Ok(pl::LazyCsvReader::new_paths(vec_pathbuf.clone().into())),
         1 => Ok(pl::LazyCsvReader::new(&vec_pathbuf[0])),
         _ => rerr().plain("path cannot have zero length").bad_arg("path"),
     }?;
-    //construct encoding parameter
-    let encoding = match encoding {
-        "utf8" => Ok(pl::CsvEncoding::Utf8),
-        "utf8-lossy" => Ok(pl::CsvEncoding::LossyUtf8),
-        _ => rerr().bad_val(format!("encoding choice: '{}' is not supported", encoding)),
-    }?;
+    let encoding = csv_encoding(encoding)?;
+    let (overwrite_dtype, positional_dtypes) = split_dtypes(dtypes)?;
+    let new_columns = robj_to!(Option, Vec, String, new_columns)?.unwrap_or_default();
+    let modify = |schema: pl::Schema| modify_schema(schema, &positional_dtypes, &new_columns);
+    let schema = robj_to!(Option, WrapSchema, schema)?
+        .map(|x| modify(x.0).map(pl::Arc::new))
+        .transpose()
+        .map_err(polars_to_rpolars_err)?;
 
-    //construct optional Schema parameter for overwrite_dtype
-    let dtv = null_to_opt(dtypes).cloned();
-    let schema = dtv.map(|some_od| {
-        let fields = some_od.0.iter().map(|(name, dtype)| {
-            if let Some(sname) = name {
-                pl::Field::new(sname.into(), dtype.clone())
-            } else {
-                todo!("missing column name for  dtype not implemented");
-            }
-        });
-        pl::Schema::from_iter(fields)
-    });
+    let hive_options = HiveOptions {
+        enabled: robj_to!(Option, bool, hive_partitioning)?,
+        hive_start_idx: 0,
+        schema: robj_to!(Option, WrapSchema, hive_schema)?.map(|x| pl::Arc::new(x.0)),
+        try_parse_dates: robj_to!(bool, try_parse_hive_dates)?,
+    };
+    let glob = robj_to!(bool, glob)?;
+    let n_rows = robj_to!(Option, usize, n_rows)?;
+    let include_file_paths = robj_to!(Option, String, include_file_paths)?.map(|x| x.into());
+
+    let infer_schema_length = robj_to!(Option, usize, infer_schema_length)?;
+    let has_header = robj_to!(bool, has_header)?;
+    let skip_rows = robj_to!(usize, skip_rows)?;
+    let skip_rows_after_header = robj_to!(usize, skip_rows_after_header)?;
+    let quote_char = robj_to!(Option, Utf8Byte, quote_char)?;
+    let eol_char = robj_to!(Utf8Byte, eol_char)?;
+    // the lines of a compressed file that are decompressed to infer its schema
+    let infer_lines = infer_schema_length
+        .map(|n| skip_rows + usize::from(has_header) + skip_rows_after_header + n.max(1));
 
-    linereader
-        .with_infer_schema_length(robj_to!(Option, usize, infer_schema_length)?)
+    let reader = linereader
+        .with_infer_schema_length(infer_schema_length)
         .with_separator(robj_to!(Utf8Byte, separator)?)
-        .with_has_header(robj_to!(bool, has_header)?)
+        .with_has_header(has_header)
         .with_ignore_errors(robj_to!(bool, ignore_errors)?)
-        .with_skip_rows(robj_to!(usize, skip_rows)?)
-        .with_n_rows(robj_to!(Option, usize, n_rows)?)
+        .with_skip_rows(skip_rows)
         .with_cache(robj_to!(bool, cache)?)
-        .with_dtype_overwrite(schema.map(|schema| std::sync::Arc::new(schema)))
+        .with_schema(schema.clone())
+        .with_dtype_overwrite(overwrite_dtype.map(|schema| std::sync::Arc::new(schema)))
         .with_low_memory(robj_to!(bool, low_memory)?)
         .with_comment_prefix(robj_to!(Option, str, comment_prefix)?.map(|x| x.into()))
-        .with_quote_char(robj_to!(Option, Utf8Byte, quote_char)?)
-        .with_eol_char(robj_to!(Utf8Byte, eol_char)?)
+        .with_quote_char(quote_char)
+        .with_eol_char(eol_char)
         .with_rechunk(robj_to!(bool, rechunk)?)
-        .with_skip_rows_after_header(robj_to!(usize, skip_rows_after_header)?)
+        .with_skip_rows_after_header(skip_rows_after_header)
         .with_encoding(encoding)
         .with_try_parse_dates(robj_to!(bool, try_parse_dates)?)
         .with_null_values(Wrap(null_values).into())
-        // .with_missing_is_null(!robj_to!(bool, missing_utf8_is_empty_string)?)
-        .with_row_index(opt_rowcount)
+        .with_missing_is_null(robj_to!(bool, missing_is_null)?)
         .with_truncate_ragged_lines(robj_to!(bool, truncate_ragged_lines)?)
-        .with_include_file_paths(robj_to!(Option, String, include_file_paths)?.map(|x| x.into()))
         .with_raise_if_empty(robj_to!(bool, raise_if_empty)?)
+        .with_cloud_options(cloud_options.clone())
+        .with_glob(glob);
+    // without a full schema, positional dtypes and new names are applied to the inferred schema
+    let reader = if schema.is_none() && !(positional_dtypes.is_empty() && new_columns.is_empty()) {
+        reader
+            .with_schema_modify(modify)
+            .map_err(polars_to_rpolars_err)?
+    } else {
+        reader
+    };
+
+    let file_reader = TextReader {
+        reader: reader.clone().with_glob(false),
+        eol_char,
+        quote_char,
+    };
+    let file_cloud_options = cloud_options.clone();
+    let hive_lf = scan_hive_partitioned(
+        &vec_pathbuf,
+        glob,
+        cloud_options.as_ref(),
+        hive_options,
+        n_rows,
+        opt_rowcount.clone(),
+        include_file_paths.clone(),
+        move |path| {
+            scan_file(
+                file_reader.clone(),
+                path,
+                file_cloud_options.as_ref(),
+                infer_lines,
+            )
+        },
+    )
+    .map_err(polars_to_rpolars_err)?;
+    if let Some(lf) = hive_lf {
+        return Ok(RPolarsLazyFrame(lf));
+    }
+    let decompressed_lf = scan_decompressed(
+        TextReader {
+            reader: reader.clone().with_glob(false),
+            eol_char,
+            quote_char,
+        },
+        &vec_pathbuf,
+        glob,
+        cloud_options.as_ref(),
+        infer_lines,
+        n_rows,
+        opt_rowcount.clone(),
+        include_file_paths.clone(),
+    )
+    .map_err(polars_to_rpolars_err)?;
+    if let Some(lf) = decompressed_lf {
+        return Ok(RPolarsLazyFrame(lf));
+    }
+
+    reader
+        .with_n_rows(n_rows)
+        .with_row_index(opt_rowcount)
+        .with_include_file_paths(include_file_paths)
         .finish()
         .map_err(polars_to_rpolars_err)
         .map(RPolarsLazyFrame)
 }
 
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn read_csv_from_raw(
+    bits: Robj,
+    has_header: Robj,
+    separator: Robj,
+    comment_prefix: Robj,
+    quote_char: Robj,
+    skip_rows: Robj,
+    dtypes: Nullable<&RPolarsDataTypeVector>,
+    null_values: Nullable<&RPolarsRNullValues>,
+    missing_is_null: Robj,
+    ignore_errors: Robj,
+    infer_schema_length: Robj,
+    n_rows: Robj,
+    encoding: &str,
+    low_memory: Robj,
+    rechunk: Robj,
+    skip_rows_after_header: Robj,
+    row_index_name: Robj,
+    row_index_offset: Robj,
+    try_parse_dates: Robj,
+    eol_char: Robj,
+    raise_if_empty: Robj,
+    truncate_ragged_lines: Robj,
+    schema: Robj,
+    new_columns: Robj,
+) -> RResult<RPolarsDataFrame> {
+    let bits = robj_to!(Raw, bits)?;
+    let options = EagerCsvOptions::new(
+        has_header,
+        separator,
+        comment_prefix,
+        quote_char,
+        skip_rows,
+        dtypes,
+        null_values,
+        missing_is_null,
+        ignore_errors,
+        infer_schema_length,
+        n_rows,
+        encoding,
+        low_memory,
+        skip_rows_after_header,
+        row_index_name,
+        row_index_offset,
+        try_parse_dates,
+        eol_char,
+        raise_if_empty,
+        truncate_ragged_lines,
+        schema,
+        new_columns,
+    )?;
+    let df = options
+        .read_options
+        .clone()
+        .with_rechunk(robj_to!(bool, rechunk)?)
+        .into_reader_with_file_handle(std::io::Cursor::new(bits))
+        .finish()
+        .and_then(|df| options.finish_frame(df, 0))
+        .map_err(polars_to_rpolars_err)?;
+    Ok(RPolarsDataFrame(df))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn csv_schema(
+    source: Robj,
+    has_header: Robj,
+    separator: Robj,
+    comment_prefix: Robj,
+    quote_char: Robj,
+    skip_rows: Robj,
+    dtypes: Nullable<&RPolarsDataTypeVector>,
+    null_values: Nullable<&RPolarsRNullValues>,
+    missing_is_null: Robj,
+    infer_schema_length: Robj,
+    encoding: &str,
+    skip_rows_after_header: Robj,
+    try_parse_dates: Robj,
+    eol_char: Robj,
+    raise_if_empty: Robj,
+    truncate_ragged_lines: Robj,
+    new_columns: Robj,
+) -> RResult<Pairlist> {
+    let options = EagerCsvOptions::new(
+        has_header,
+        separator,
+        comment_prefix,
+        quote_char,
+        skip_rows,
+        dtypes,
+        null_values,
+        missing_is_null,
+        false.into(),
+        infer_schema_length,
+        NULL.into(),
+        encoding,
+        false.into(),
+        skip_rows_after_header,
+        NULL.into(),
+        NULL.into(),
+        try_parse_dates,
+        eol_char,
+        raise_if_empty,
+        truncate_ragged_lines,
+        NULL.into(),
+        new_columns,
+    )?;
+    let schema = if source.rtype() == Rtype::Raw {
+        infer_csv_schema(std::io::Cursor::new(robj_to!(Raw, source)?), &options)
+    } else {
+        let path = robj_to!(String, source)?;
+        let file = std::fs::File::open(&path)
+            .map_err(|err| RPolarsErr::new().plain(format!("failed to open '{path}': {err}")))?;
+        infer_csv_schema(file, &options)
+    }
+    .map_err(polars_to_rpolars_err)?;
+    Ok(schema_to_pairlist(&schema))
+}
+
+// only the rows needed for the inference are parsed, like the readers do before reading the data
+fn infer_csv_schema<R: polars::io::mmap::MmapBytesReader>(
+    mut reader: R,
+    options: &EagerCsvOptions,
+) -> pl::PolarsResult<pl::Schema> {
+    let opts = &options.read_options;
+    let reader_bytes = polars::io::mmap::ReaderBytes::from(&mut reader);
+    let (schema, _, _) = polars::io::csv::read::infer_file_schema(
+        &reader_bytes,
+        &opts.parse_options,
+        opts.infer_schema_length,
+        opts.has_header,
+        opts.schema_overwrite.as_deref(),
+        opts.skip_rows,
+        opts.skip_lines,
+        opts.skip_rows_after_header,
+        opts.raise_if_empty,
+        &mut None,
+    )?;
+    let dtypes = opts
+        .dtype_overwrite
+        .as_deref()
+        .map_or(&[][..], |x| x.as_slice());
+    modify_schema(schema, dtypes, &options.new_columns)
+}
+
+// Reads a CSV file or raw vector in batches of DataFrames, see `next_batches()`
+pub struct RPolarsBatchedCsvReader {
+    reader: pl::OwnedBatchedCsvReader,
+    options: EagerCsvOptions,
+    rows_read: u32,
+}
+
+#[extendr]
+impl RPolarsBatchedCsvReader {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        source: Robj,
+        batch_size: Robj,
+        has_header: Robj,
+        separator: Robj,
+        comment_prefix: Robj,
+        quote_char: Robj,
+        skip_rows: Robj,
+        dtypes: Nullable<&RPolarsDataTypeVector>,
+        null_values: Nullable<&RPolarsRNullValues>,
+        missing_is_null: Robj,
+        ignore_errors: Robj,
+        infer_schema_length: Robj,
+        n_rows: Robj,
+        encoding: &str,
+        low_memory: Robj,
+        skip_rows_after_header: Robj,
+        row_index_name: Robj,
+        row_index_offset: Robj,
+        try_parse_dates: Robj,
+        eol_char: Robj,
+        raise_if_empty: Robj,
+        truncate_ragged_lines: Robj,
+        schema: Robj,
+        new_columns: Robj,
+    ) -> RResult<Self> {
+        use polars::io::mmap::MmapBytesReader;
+        let handle: Box<dyn MmapBytesReader> = if source.rtype() == Rtype::Raw {
+            Box::new(std::io::Cursor::new(robj_to!(Raw, source)?))
+        } else {
+            let path = robj_to!(String, source)?;
+            let file = std::fs::File::open(&path).map_err(|err| {
+                RPolarsErr::new().plain(format!("failed to open '{path}': {err}"))
+            })?;
+            Box::new(file)
+        };
+        let options = EagerCsvOptions::new(
+            has_header,
+            separator,
+            comment_prefix,
+            quote_char,
+            skip_rows,
+            dtypes,
+            null_values,
+            missing_is_null,
+            ignore_errors,
+            infer_schema_length,
+            n_rows,
+            encoding,
+            low_memory,
+            skip_rows_after_header,
+            row_index_name,
+            row_index_offset,
+            try_parse_dates,
+            eol_char,
+            raise_if_empty,
+            truncate_ragged_lines,
+            schema,
+            new_columns,
+        )?;
+        let reader = options
+            .read_options
+            .clone()
+            .with_chunk_size(robj_to!(usize, batch_size)?)
+            .into_reader_with_file_handle(handle)
+            .batched(None)
+            .map_err(polars_to_rpolars_err)?;
+        Ok(RPolarsBatchedCsvReader {
+            reader,
+            options,
+            rows_read: 0,
+        })
+    }
+
+    // returns NULL once the source is exhausted
+    pub fn next_batches(&mut self, n: Robj) -> RResult<Robj> {
+        let Some(batches) = self
+            .reader
+            .next_batches(robj_to!(usize, n)?)
+            .map_err(polars_to_rpolars_err)?
+        else {
+            return Ok(NULL.into());
+        };
+        let batches = batches
+            .into_iter()
+            .map(|df| {
+                let height = df.height() as u32;
+                let df = self.options.finish_frame(df, self.rows_read)?;
+                self.rows_read += height;
+                Ok(RPolarsDataFrame(df))
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()
+            .map_err(polars_to_rpolars_err)?;
+        Ok(List::from_values(batches).into())
+    }
+}
+
+// The options of the eager CSV readers. The row index is added after renaming the columns, so
+// that new_columns only refer to the columns of the file.
+struct EagerCsvOptions {
+    read_options: pl::CsvReadOptions,
+    new_columns: Vec<String>,
+    row_index: Option<RowIndex>,
+}
+
+impl EagerCsvOptions {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        has_header: Robj,
+        separator: Robj,
+        comment_prefix: Robj,
+        quote_char: Robj,
+        skip_rows: Robj,
+        dtypes: Nullable<&RPolarsDataTypeVector>,
+        null_values: Nullable<&RPolarsRNullValues>,
+        missing_is_null: Robj,
+        ignore_errors: Robj,
+        infer_schema_length: Robj,
+        n_rows: Robj,
+        encoding: &str,
+        low_memory: Robj,
+        skip_rows_after_header: Robj,
+        row_index_name: Robj,
+        row_index_offset: Robj,
+        try_parse_dates: Robj,
+        eol_char: Robj,
+        raise_if_empty: Robj,
+        truncate_ragged_lines: Robj,
+        schema: Robj,
+        new_columns: Robj,
+    ) -> RResult<Self> {
+        let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
+        let row_index = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
+            name: name.into(),
+            offset,
+        });
+
+        let encoding = csv_encoding(encoding)?;
+        let (overwrite_dtype, positional_dtypes) = split_dtypes(dtypes)?;
+        let new_columns = robj_to!(Option, Vec, String, new_columns)?.unwrap_or_default();
+        let schema = robj_to!(Option, WrapSchema, schema)?
+            .map(|x| modify_schema(x.0, &positional_dtypes, &new_columns).map(pl::Arc::new))
+            .transpose()
+            .map_err(polars_to_rpolars_err)?;
+        // a full schema already carries the positional dtypes and new names
+        let (positional_dtypes, new_columns) = match schema {
+            Some(_) => (vec![], vec![]),
+            None => (positional_dtypes, new_columns),
+        };
+
+        let separator = robj_to!(Utf8Byte, separator)?;
+        let quote_char = robj_to!(Option, Utf8Byte, quote_char)?;
+        let eol_char = robj_to!(Utf8Byte, eol_char)?;
+        let comment_prefix = robj_to!(Option, str, comment_prefix)?;
+        let null_values: Option<pl::NullValues> = Wrap(null_values).into();
+        let missing_is_null = robj_to!(bool, missing_is_null)?;
+        let truncate_ragged_lines = robj_to!(bool, truncate_ragged_lines)?;
+        let try_parse_dates = robj_to!(bool, try_parse_dates)?;
+
+        let read_options = pl::CsvReadOptions::default()
+            .with_has_header(robj_to!(bool, has_header)?)
+            .with_skip_rows(robj_to!(usize, skip_rows)?)
+            .with_skip_rows_after_header(robj_to!(usize, skip_rows_after_header)?)
+            .with_infer_schema_length(robj_to!(Option, usize, infer_schema_length)?)
+            .with_ignore_errors(robj_to!(bool, ignore_errors)?)
+            .with_n_rows(robj_to!(Option, usize, n_rows)?)
+            .with_low_memory(robj_to!(bool, low_memory)?)
+            .with_raise_if_empty(robj_to!(bool, raise_if_empty)?)
+            .with_schema(schema)
+            .with_schema_overwrite(overwrite_dtype.map(pl::Arc::new))
+            .with_dtype_overwrite(
+                (!positional_dtypes.is_empty()).then(|| pl::Arc::new(positional_dtypes)),
+            )
+            .map_parse_options(|opts| {
+                opts.with_separator(separator)
+                    .with_quote_char(quote_char)
+                    .with_eol_char(eol_char)
+                    .with_encoding(encoding)
+                    .with_comment_prefix(comment_prefix)
+                    .with_null_values(null_values.clone())
+                    .with_missing_is_null(missing_is_null)
+                    .with_truncate_ragged_lines(truncate_ragged_lines)
+                    .with_try_parse_dates(try_parse_dates)
+            });
+
+        Ok(EagerCsvOptions {
+            read_options,
+            new_columns,
+            row_index,
+        })
+    }
+
+    // rename the columns and add the row index, `rows_read` is the number of rows read before `df`
+    fn finish_frame(
+        &self,
+        mut df: pl::DataFrame,
+        rows_read: u32,
+    ) -> pl::PolarsResult<pl::DataFrame> {
+        if !self.new_columns.is_empty() {
+            let schema = modify_schema(df.schema().as_ref().clone(), &[], &self.new_columns)?;
+            df.set_column_names(schema.iter_names_cloned())?;
+        }
+        match &self.row_index {
+            Some(row_index) => {
+                df.with_row_index(row_index.name.clone(), Some(row_index.offset + rows_read))
+            }
+            None => Ok(df),
+        }
+    }
+}
+
+fn csv_encoding(encoding: &str) -> RResult<pl::CsvEncoding> {
+    match encoding {
+        "utf8" => Ok(pl::CsvEncoding::Utf8),
+        "utf8-lossy" => Ok(pl::CsvEncoding::LossyUtf8),
+        _ => rerr().bad_val(format!("encoding choice: '{}' is not supported", encoding)),
+    }
+}
+
+//dtypes are either all named and overwrite by name, or all unnamed and overwrite by position
+fn split_dtypes(
+    dtypes: Nullable<&RPolarsDataTypeVector>,
+) -> RResult<(Option<pl::Schema>, Vec<pl::DataType>)> {
+    let dtv = null_to_opt(dtypes)
+        .map(|dtv| dtv.0.clone())
+        .unwrap_or_default();
+    let n_named = dtv.iter().filter(|(name, _)| name.is_some()).count();
+    match n_named {
+        0 => Ok((None, dtv.into_iter().map(|(_, dtype)| dtype).collect())),
+        n if n == dtv.len() => {
+            let fields = dtv
+                .into_iter()
+                .filter_map(|(name, dtype)| Some(pl::Field::new(name?.into(), dtype)));
+            Ok((Some(pl::Schema::from_iter(fields)), vec![]))
+        }
+        _ => rerr()
+            .plain("dtypes must be either all named or all unnamed")
+            .bad_arg("dtypes"),
+    }
+}
+
+fn modify_schema(
+    schema: pl::Schema,
+    dtypes: &[pl::DataType],
+    new_columns: &[String],
+) -> pl::PolarsResult<pl::Schema> {
+    pl::polars_ensure!(
+        dtypes.len() <= schema.len(),
+        ComputeError: "{} dtypes were given, but the CSV file has only {} columns",
+        dtypes.len(), schema.len()
+    );
+    pl::polars_ensure!(
+        new_columns.len() <= schema.len(),
+        ComputeError: "{} new column names were given, but the CSV file has only {} columns",
+        new_columns.len(), schema.len()
+    );
+    Ok(schema
+        .iter()
+        .enumerate()
+        .map(|(i, (name, dtype))| {
+            let name = new_columns
+                .get(i)
+                .map_or_else(|| name.clone(), |new| new.as_str().into());
+            pl::Field::new(name, dtypes.get(i).unwrap_or(dtype).clone())
+        })
+        .collect())
+}
+
 extendr_module! {
     mod read_csv;
     fn new_from_csv;
+    fn read_csv_from_raw;
+    fn csv_schema;
     impl RPolarsRNullValues;
+    impl RPolarsBatchedCsvReader;
 }
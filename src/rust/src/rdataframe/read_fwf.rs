@@ -0,0 +1,298 @@
+//read fixed-width files
+
+use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::compression::maybe_decompress_reader;
+use crate::rdataframe::delta::read_file;
+use crate::rdatatype::robj_to_cloud_options;
+use crate::robj_to;
+use crate::rpolarserr::*;
+
+use either::Either;
+use extendr_api::{extendr, extendr_module, Robj};
+use polars::io::cloud::CloudOptions;
+use polars::io::path_utils::is_cloud_url;
+use polars::io::RowIndex;
+use polars::prelude::{self as pl, Arc, IntoSeries, PlSmallStr};
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::PathBuf;
+
+// the fields of a line are sliced by byte positions, so the file is read line by line in an
+// anonymous scan which receives the pushed down projection and slice from the optimizer
+struct FwfScan {
+    path: PathBuf,
+    cloud_options: Option<CloudOptions>,
+    schema: pl::SchemaRef,
+    // byte offset and width of every column of the schema
+    positions: Vec<(usize, usize)>,
+    skip_rows: usize,
+    trim_whitespace: bool,
+    missing_is_null: bool,
+    lossy: bool,
+}
+
+impl FwfScan {
+    fn open(&self) -> pl::PolarsResult<Box<dyn BufRead>> {
+        let file = if is_cloud_url(&self.path) {
+            let bytes = read_file(&self.path, self.cloud_options.as_ref())?;
+            Either::Right(Cursor::new(bytes))
+        } else {
+            Either::Left(std::fs::File::open(&self.path)?)
+        };
+        Ok(maybe_decompress_reader(BufReader::new(file))?)
+    }
+
+    fn field<'a>(&self, line: &'a [u8], idx: usize) -> &'a [u8] {
+        let (offset, width) = self.positions[idx];
+        let start = offset.min(line.len());
+        let end = offset.saturating_add(width).min(line.len());
+        &line[start..end]
+    }
+}
+
+impl pl::AnonymousScan for FwfScan {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: pl::AnonymousScanArgs) -> pl::PolarsResult<pl::DataFrame> {
+        // read projected columns in file order, the optimizer reorders them afterwards
+        let projection: Vec<usize> = match scan_opts.with_columns {
+            Some(columns) => {
+                let mut idx: Vec<usize> = columns
+                    .iter()
+                    .filter_map(|name| self.schema.index_of(name))
+                    .collect();
+                idx.sort_unstable();
+                idx
+            }
+            None => (0..self.schema.len()).collect(),
+        };
+        let n_rows = scan_opts.n_rows.unwrap_or(usize::MAX);
+
+        let mut builders: Vec<pl::StringChunkedBuilder> = projection
+            .iter()
+            .map(|&i| {
+                let (name, _) = self.schema.get_at_index(i).unwrap();
+                pl::StringChunkedBuilder::new(name.clone(), 1024)
+            })
+            .collect();
+
+        let mut reader = self.open()?;
+        let mut buf = Vec::new();
+        let mut line_no = 0;
+        let mut height = 0;
+        while height < n_rows {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line_no += 1;
+            let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if line_no <= self.skip_rows || line.is_empty() {
+                continue;
+            }
+            for (builder, &i) in builders.iter_mut().zip(&projection) {
+                let bytes = self.field(line, i);
+                let value = if self.lossy {
+                    String::from_utf8_lossy(bytes)
+                } else {
+                    match std::str::from_utf8(bytes) {
+                        Ok(value) => value.into(),
+                        Err(_) => pl::polars_bail!(
+                            ComputeError: "invalid utf-8 in column '{}' on line {}, \
+                            check the offsets and widths or set `encoding = \"utf8-lossy\"`",
+                            self.schema.get_at_index(i).unwrap().0, line_no
+                        ),
+                    }
+                };
+                let value = if self.trim_whitespace {
+                    value.trim()
+                } else {
+                    value.as_ref()
+                };
+                if value.is_empty() && self.missing_is_null {
+                    builder.append_null();
+                } else {
+                    builder.append_value(value);
+                }
+            }
+            height += 1;
+        }
+
+        let columns = builders
+            .into_iter()
+            .zip(&projection)
+            .map(|(builder, &i)| {
+                let dtype = self.schema.get_at_index(i).unwrap().1;
+                builder
+                    .finish()
+                    .into_series()
+                    .strict_cast(dtype)
+                    .map(pl::Column::from)
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+        pl::DataFrame::new(columns)
+    }
+
+    fn schema(&self, _infer_schema_length: Option<usize>) -> pl::PolarsResult<pl::SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_slice_pushdown(&self) -> bool {
+        true
+    }
+}
+
+// the byte offset and width of each column, from the widths of consecutive columns or from
+// the offsets where the last column runs to the end of the line
+fn column_positions(
+    n_columns: usize,
+    offsets: Option<Vec<usize>>,
+    widths: Option<Vec<usize>>,
+) -> RResult<Vec<(usize, usize)>> {
+    let check_len = |x: &Vec<usize>, arg: &str| {
+        if x.len() == n_columns {
+            Ok(())
+        } else {
+            rerr()
+                .plain(format!(
+                    "must have the same length as `columns` ({n_columns}), got {}",
+                    x.len()
+                ))
+                .bad_arg(arg)
+        }
+    };
+    let positions: Vec<(usize, usize)> = match (offsets, widths) {
+        (None, None) => {
+            return rerr()
+                .plain("either `offsets` or `widths` must be set")
+                .bad_arg("widths")
+        }
+        (Some(offsets), Some(widths)) => {
+            check_len(&offsets, "offsets")?;
+            check_len(&widths, "widths")?;
+            offsets.into_iter().zip(widths).collect()
+        }
+        (None, Some(widths)) => {
+            check_len(&widths, "widths")?;
+            widths
+                .iter()
+                .scan(0, |offset, &width| {
+                    let start = *offset;
+                    *offset += width;
+                    Some((start, width))
+                })
+                .collect()
+        }
+        (Some(offsets), None) => {
+            check_len(&offsets, "offsets")?;
+            let ends = offsets.iter().skip(1).copied().chain([usize::MAX]);
+            offsets
+                .iter()
+                .zip(ends)
+                .map(|(&start, end)| (start, end.saturating_sub(start)))
+                .collect()
+        }
+    };
+    if positions.iter().any(|&(_, width)| width == 0) {
+        return rerr()
+            .plain("columns must have a width of at least 1 byte, are the offsets increasing?")
+            .bad_arg("widths");
+    }
+    Ok(positions)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn new_from_fwf(
+    path: Robj,
+    columns: Robj,
+    offsets: Robj,
+    widths: Robj,
+    dtypes: Robj,
+    skip_rows: Robj,
+    n_rows: Robj,
+    row_index_name: Robj,
+    row_index_offset: Robj,
+    trim_whitespace: Robj,
+    missing_is_null: Robj,
+    encoding: Robj,
+    storage_options: Robj,
+) -> RResult<RPolarsLazyFrame> {
+    let names = robj_to!(Vec, String, columns)?;
+    let positions = column_positions(
+        names.len(),
+        robj_to!(Option, Vec, usize, offsets)?,
+        robj_to!(Option, Vec, usize, widths)?,
+    )?;
+
+    let mut schema = pl::Schema::with_capacity(names.len());
+    for name in names {
+        let name = PlSmallStr::from(name);
+        if schema.contains(&name) {
+            return rerr()
+                .plain(format!("column name '{name}' is duplicated"))
+                .bad_arg("columns");
+        }
+        schema.with_column(name, pl::DataType::String);
+    }
+    if let Some(dtypes) = robj_to!(Option, WrapSchema, dtypes)? {
+        for (name, dtype) in dtypes.0.iter() {
+            if !schema.contains(name) {
+                return rerr()
+                    .plain(format!("column '{name}' is not one of `columns`"))
+                    .bad_arg("dtypes");
+            }
+            schema.with_column(name.clone(), dtype.clone());
+        }
+    }
+
+    let lossy = match robj_to!(str, encoding)? {
+        "utf8" => false,
+        "utf8-lossy" => true,
+        e => {
+            return rerr().notachoice(format!(
+                "encoding choice: '{e}' is not supported, must be one of 'utf8', 'utf8-lossy'"
+            ))
+        }
+    };
+    let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
+    let opt_row_index = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+
+    let path = robj_to!(PathBuf, path)?;
+    let cloud_options = robj_to_cloud_options(&path.to_string_lossy(), &storage_options)?;
+    let scan = FwfScan {
+        path,
+        cloud_options,
+        schema: Arc::new(schema),
+        positions,
+        skip_rows: robj_to!(usize, skip_rows)?,
+        trim_whitespace: robj_to!(bool, trim_whitespace)?,
+        missing_is_null: robj_to!(bool, missing_is_null)?,
+        lossy,
+    };
+    let args = pl::ScanArgsAnonymous {
+        schema: Some(scan.schema.clone()),
+        n_rows: robj_to!(Option, usize, n_rows)?,
+        row_index: opt_row_index,
+        name: "FWF SCAN",
+        ..Default::default()
+    };
+
+    pl::LazyFrame::anonymous_scan(Arc::new(scan), args)
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsLazyFrame)
+}
+
+extendr_module! {
+    mod read_fwf;
+    fn new_from_fwf;
+}
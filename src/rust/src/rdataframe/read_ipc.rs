@@ -1,10 +1,18 @@
 use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::RPolarsDataFrame;
+use crate::rdatatype::{robj_to_cloud_options, schema_to_pairlist};
 use crate::robj_to;
-use crate::rpolarserr::RResult;
+use crate::rpolarserr::*;
 use extendr_api::prelude::*;
-use polars::io::{HiveOptions, RowIndex};
-use polars::prelude::{Arc, LazyFrame, ScanArgsIpc};
+use polars::io::cloud::CloudOptions;
+use polars::io::ipc::IpcReaderAsync;
+use polars::io::path_utils::{expand_paths_hive, expanded_from_single_directory, is_cloud_url};
+use polars::io::{pl_async, HiveOptions, RowIndex};
+use polars::lazy::dsl;
+use polars::prelude::{self as pl, Arc, LazyFrame, ScanArgsIpc, SchemaExt, SerReader};
+use std::path::{Path, PathBuf};
 
+#[allow(clippy::too_many_arguments)]
 #[extendr]
 pub fn import_arrow_ipc(
     path: Robj,
@@ -17,13 +25,23 @@ pub fn import_arrow_ipc(
     hive_schema: Robj,
     try_parse_hive_dates: Robj,
     include_file_paths: Robj,
+    storage_options: Robj,
+    schema: Robj,
+    allow_missing_columns: Robj,
 ) -> RResult<RPolarsLazyFrame> {
+    let paths = robj_to!(Vec, PathBuf, path)?;
+    if paths.is_empty() {
+        return rerr().plain("path cannot have zero length").bad_arg("path");
+    }
+    let cloud_options = robj_to_cloud_options(&paths[0].to_string_lossy(), &storage_options)?;
     let hive_options = HiveOptions {
         enabled: robj_to!(Option, bool, hive_partitioning)?,
         hive_start_idx: 0,
         schema: robj_to!(Option, WrapSchema, hive_schema)?.map(|x| Arc::new(x.0)),
         try_parse_dates: robj_to!(bool, try_parse_hive_dates)?,
     };
+    let schema = robj_to!(Option, WrapSchema, schema)?;
+    let allow_missing_columns = robj_to!(bool, allow_missing_columns)?;
 
     let args = ScanArgsIpc {
         n_rows: robj_to!(Option, usize, n_rows)?,
@@ -37,16 +55,212 @@ pub fn import_arrow_ipc(
                 })
             })
             .transpose()?,
-        cloud_options: None,
+        cloud_options,
         hive_options,
         include_file_paths: robj_to!(Option, String, include_file_paths)?.map(|x| x.into()),
     };
-    let lf = LazyFrame::scan_ipc(robj_to!(String, path)?, args)
-        .map_err(crate::rpolarserr::polars_to_rpolars_err)?;
+
+    // the ipc scan has no schema argument, so the footer of every file is checked against it
+    let lf = if schema.is_some() || allow_missing_columns {
+        scan_ipc_diagonal(
+            &paths,
+            args,
+            schema.as_ref().map(|x| &x.0),
+            allow_missing_columns,
+        )
+    } else {
+        LazyFrame::scan_ipc_files(paths.into(), args)
+    }
+    .map_err(polars_to_rpolars_err)?;
+
     Ok(RPolarsLazyFrame(lf))
 }
 
+// The native ipc scan requires all files to share one schema. To allow missing columns, every
+// file is scanned on its own and the frames are stacked diagonally. Hive partitions are still
+// parsed from the full path of each file, row index and n_rows are applied after stacking.
+// With a schema, the columns of every file must be in it with the same type, missing columns
+// are only allowed with `allow_missing_columns` and are read as nulls of the given type.
+fn scan_ipc_diagonal(
+    paths: &[PathBuf],
+    args: ScanArgsIpc,
+    schema: Option<&pl::Schema>,
+    allow_missing_columns: bool,
+) -> pl::PolarsResult<LazyFrame> {
+    let mut hive_options = args.hive_options.clone();
+    let (expanded, _) = expand_paths_hive(
+        paths,
+        true,
+        args.cloud_options.as_ref(),
+        hive_options.enabled.unwrap_or(false),
+    )?;
+    if hive_options.enabled.is_none() {
+        hive_options.enabled = Some(expanded_from_single_directory(paths, expanded.as_ref()));
+    }
+
+    let file_args = ScanArgsIpc {
+        n_rows: None,
+        row_index: None,
+        rechunk: false,
+        hive_options,
+        ..args.clone()
+    };
+    let lfs = expanded
+        .iter()
+        .map(|path| {
+            let lf = LazyFrame::scan_ipc(path, file_args.clone())?;
+            let Some(schema) = schema else {
+                return Ok(lf);
+            };
+            let file_schema = ipc_file_schema(path, args.cloud_options.as_ref())?;
+            check_ipc_schema(path, &file_schema, schema, allow_missing_columns)?;
+            let missing = schema
+                .iter()
+                .filter(|(name, _)| !file_schema.contains(name))
+                .map(|(name, dtype)| pl::lit(pl::Null {}).cast(dtype.clone()).alias(name.clone()))
+                .collect::<Vec<_>>();
+            Ok(lf.with_columns(missing))
+        })
+        .collect::<pl::PolarsResult<Vec<_>>>()?;
+    let mut lf = dsl::concat_lf_diagonal(
+        lfs,
+        pl::UnionArgs {
+            rechunk: args.rechunk,
+            ..Default::default()
+        },
+    )?;
+    // the columns are in the order of the schema, followed by the hive and file path columns
+    if let Some(schema) = schema {
+        let names = schema.iter_names_cloned().collect::<Vec<_>>();
+        let exprs = names
+            .iter()
+            .map(|name| pl::col(name.clone()))
+            .chain(std::iter::once(pl::all().exclude(names.clone())))
+            .collect::<Vec<_>>();
+        lf = lf.select(exprs);
+    }
+    if let Some(row_index) = args.row_index {
+        lf = lf.with_row_index(row_index.name, Some(row_index.offset));
+    }
+    if let Some(n_rows) = args.n_rows {
+        lf = lf.limit(n_rows as pl::IdxSize);
+    }
+    Ok(lf)
+}
+
+// only the footer of the file is read, cloud files are not downloaded
+fn ipc_file_schema(
+    path: &Path,
+    cloud_options: Option<&CloudOptions>,
+) -> pl::PolarsResult<pl::Schema> {
+    let arrow_schema = if is_cloud_url(path) {
+        let uri = path.to_string_lossy();
+        pl_async::get_runtime()
+            .block_on_potential_spawn(async {
+                IpcReaderAsync::from_uri(&uri, cloud_options)
+                    .await?
+                    .metadata()
+                    .await
+            })?
+            .schema
+    } else {
+        pl::IpcReader::new(std::fs::File::open(path)?).schema()?
+    };
+    Ok(pl::Schema::from_arrow_schema(&arrow_schema))
+}
+
+fn check_ipc_schema(
+    path: &Path,
+    file_schema: &pl::Schema,
+    schema: &pl::Schema,
+    allow_missing_columns: bool,
+) -> pl::PolarsResult<()> {
+    for (name, dtype) in file_schema.iter() {
+        match schema.get(name) {
+            None => pl::polars_bail!(
+                SchemaMismatch: "the column '{}' of the file '{}' is not in the given schema",
+                name, path.display()
+            ),
+            Some(expected) if expected != dtype => pl::polars_bail!(
+                SchemaMismatch: "the column '{}' of the file '{}' has type {}, but the given schema has type {}",
+                name, path.display(), dtype, expected
+            ),
+            Some(_) => {}
+        }
+    }
+    if !allow_missing_columns {
+        if let Some(name) = schema.iter_names().find(|name| !file_schema.contains(name)) {
+            pl::polars_bail!(
+                ColumnNotFound: "the column '{}' of the given schema is not in the file '{}', consider setting `allow_missing_columns = TRUE`",
+                name, path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[extendr]
+pub fn read_ipc_stream(
+    source: Robj,
+    n_rows: Robj,
+    columns: Robj,
+    rechunk: Robj,
+    row_name: Robj,
+    row_index: Robj,
+) -> RResult<RPolarsDataFrame> {
+    let n_rows = robj_to!(Option, usize, n_rows)?;
+    let columns = robj_to!(Option, Vec, String, columns)?;
+    let rechunk = robj_to!(bool, rechunk)?;
+    let row_index = robj_to!(Option, String, row_name)?
+        .map(|name| {
+            robj_to!(u32, row_index).map(|offset| RowIndex {
+                name: name.into(),
+                offset,
+            })
+        })
+        .transpose()?;
+    let finish = |reader: Box<dyn std::io::Read>| {
+        pl::IpcStreamReader::new(reader)
+            .with_n_rows(n_rows)
+            .with_columns(columns)
+            .with_row_index(row_index)
+            .set_rechunk(rechunk)
+            .finish()
+            .map_err(polars_to_rpolars_err)
+    };
+
+    // the stream format is read sequentially, so a raw vector is read from memory as is
+    let df = if source.rtype() == Rtype::Raw {
+        finish(Box::new(std::io::Cursor::new(robj_to!(Raw, source)?)))?
+    } else {
+        let path = robj_to!(String, source)?;
+        let file = std::fs::File::open(&path)
+            .map_err(|err| RPolarsErr::new().plain(format!("failed to open '{path}': {err}")))?;
+        finish(Box::new(std::io::BufReader::new(file)))?
+    };
+    Ok(RPolarsDataFrame(df))
+}
+
+// only the footer of the file is read
+#[extendr]
+pub fn ipc_schema(source: Robj) -> RResult<Pairlist> {
+    let arrow_schema = if source.rtype() == Rtype::Raw {
+        pl::IpcReader::new(std::io::Cursor::new(robj_to!(Raw, source)?)).schema()
+    } else {
+        let path = robj_to!(String, source)?;
+        let file = std::fs::File::open(&path)
+            .map_err(|err| RPolarsErr::new().plain(format!("failed to open '{path}': {err}")))?;
+        pl::IpcReader::new(file).schema()
+    }
+    .map_err(polars_to_rpolars_err)?;
+    Ok(schema_to_pairlist(&pl::Schema::from_arrow_schema(
+        &arrow_schema,
+    )))
+}
+
 extendr_module! {
     mod read_ipc;
     fn import_arrow_ipc;
+    fn read_ipc_stream;
+    fn ipc_schema;
 }
@@ -0,0 +1,82 @@
+//read json
+
+use crate::rdataframe::RPolarsDataFrame;
+use crate::robj_to;
+use crate::rpolarserr::*;
+use extendr_api::{extendr, prelude::*};
+use polars::prelude::{self as pl, SerReader};
+
+// `<DataFrame>$write_json()` writes a JSON array of rows with `row_oriented = TRUE`, and the
+// serde representation of the DataFrame otherwise, so both are read back here
+#[extendr]
+pub fn read_json(
+    source: Robj,
+    infer_schema_length: Robj,
+    schema: Robj,
+    schema_overrides: Robj,
+) -> RResult<RPolarsDataFrame> {
+    let bits = if source.rtype() == Rtype::Raw {
+        robj_to!(Raw, source)?
+    } else {
+        let path = robj_to!(String, source)?;
+        std::fs::read(&path)
+            .map_err(|err| RPolarsErr::new().plain(format!("failed to open '{path}': {err}")))?
+    };
+    let infer_schema_length = robj_to!(Option, nonzero_usize, infer_schema_length)?;
+    let schema = robj_to!(Option, WrapSchema, schema)?.map(|x| x.0);
+    let schema_overrides = robj_to!(Option, WrapSchema, schema_overrides)?.map(|x| x.0);
+
+    let df = if is_row_oriented(&bits) {
+        let mut reader = pl::JsonReader::new(std::io::Cursor::new(bits))
+            .with_json_format(pl::JsonFormat::Json)
+            .infer_schema_len(infer_schema_length);
+        if let Some(schema) = schema {
+            reader = reader.with_schema(pl::Arc::new(schema));
+        }
+        if let Some(schema_overrides) = &schema_overrides {
+            reader = reader.with_schema_overwrite(schema_overrides);
+        }
+        reader.finish()
+    } else {
+        serde_json::from_slice::<pl::DataFrame>(&bits)
+            .map_err(|e| pl::polars_err!(ComputeError: "{e}"))
+            .and_then(|df| cast_columns(df, schema.as_ref(), schema_overrides.as_ref()))
+    }
+    .map_err(polars_to_rpolars_err)?;
+    Ok(RPolarsDataFrame(df))
+}
+
+// rows are an array of objects, anything else is a serialized DataFrame
+fn is_row_oriented(bits: &[u8]) -> bool {
+    let bits = bits.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bits);
+    let mut bytes = bits.iter().copied().filter(|b| !b.is_ascii_whitespace());
+    matches!(
+        (bytes.next(), bytes.next()),
+        (Some(b'['), Some(b'{') | Some(b']') | None)
+    )
+}
+
+// the dtypes of a serialized DataFrame are known, so the schema only selects and casts columns
+fn cast_columns(
+    mut df: pl::DataFrame,
+    schema: Option<&pl::Schema>,
+    schema_overrides: Option<&pl::Schema>,
+) -> pl::PolarsResult<pl::DataFrame> {
+    if let Some(schema) = schema {
+        df = df.select(schema.iter_names_cloned())?;
+    }
+    for (name, dtype) in schema
+        .into_iter()
+        .chain(schema_overrides)
+        .flat_map(|s| s.iter())
+    {
+        let column = df.column(name)?.cast(dtype)?;
+        df.with_column(column)?;
+    }
+    Ok(df)
+}
+
+extendr_module! {
+    mod read_json;
+    fn read_json;
+}
@@ -1,14 +1,18 @@
 //read ndjson
 
 use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::compression::{scan_decompressed, scan_file, TextReader};
+use crate::rdataframe::hive_scan::scan_hive_partitioned;
+use crate::rdataframe::RPolarsDataFrame;
+use crate::rdatatype::{robj_to_cloud_options, schema_to_pairlist};
 use crate::robj_to;
 use crate::rpolarserr::*;
-use polars::io::RowIndex;
+use polars::io::{HiveOptions, RowIndex};
 
 //use crate::utils::wrappers::*;
 use extendr_api::{extendr, prelude::*};
 use polars::prelude as pl;
-use polars::prelude::LazyFileListReader;
+use polars::prelude::{LazyFileListReader, SerReader};
 use std::result::Result;
 
 #[allow(clippy::too_many_arguments)]
@@ -23,6 +27,13 @@ pub fn new_from_ndjson(
     row_index_name: Robj,
     row_index_offset: Robj,
     ignore_errors: Robj,
+    storage_options: Robj,
+    include_file_paths: Robj,
+    hive_partitioning: Robj,
+    hive_schema: Robj,
+    try_parse_hive_dates: Robj,
+    glob: Robj,
+    schema: Robj,
 ) -> RResult<RPolarsLazyFrame> {
     let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
     let opt_rowindex = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
@@ -31,26 +42,166 @@ pub fn new_from_ndjson(
     });
 
     let vec_pathbuf = robj_to!(Vec, PathBuf, path)?;
+    let cloud_options = vec_pathbuf
+        .first()
+        .map(|first| robj_to_cloud_options(&first.to_string_lossy(), &storage_options))
+        .transpose()?
+        .flatten();
     let linereader = match vec_pathbuf.len() {
-        2.. => Ok(pl::LazyJsonLineReader::new_paths(vec_pathbuf.into())),
+        2.. => Ok(pl::LazyJsonLineReader::new_paths(
+            vec_pathbuf.clone().into(),
+        )),
         1 => Ok(pl::LazyJsonLineReader::new(&vec_pathbuf[0])),
         _ => rerr().plain("path cannot have zero length").bad_arg("path"),
     }?;
 
-    linereader
-        .with_infer_schema_length(robj_to!(Option, nonzero_usize, infer_schema_length)?)
+    let hive_options = HiveOptions {
+        enabled: robj_to!(Option, bool, hive_partitioning)?,
+        hive_start_idx: 0,
+        schema: robj_to!(Option, WrapSchema, hive_schema)?.map(|x| pl::Arc::new(x.0)),
+        try_parse_dates: robj_to!(bool, try_parse_hive_dates)?,
+    };
+    let glob = robj_to!(bool, glob)?;
+    let n_rows = robj_to!(Option, usize, n_rows)?;
+    let include_file_paths = robj_to!(Option, String, include_file_paths)?.map(|x| x.into());
+
+    let infer_schema_length = robj_to!(Option, nonzero_usize, infer_schema_length)?;
+    // the lines of a compressed file that are decompressed to infer its schema
+    let infer_lines = infer_schema_length.map(|n| n.get());
+
+    let reader = linereader
+        .with_infer_schema_length(infer_schema_length)
         .with_batch_size(robj_to!(Option, nonzero_usize, batch_size)?)
-        .with_n_rows(robj_to!(Option, usize, n_rows)?)
         .low_memory(robj_to!(bool, low_memory)?)
-        .with_row_index(opt_rowindex)
         .with_rechunk(robj_to!(bool, rechunk)?)
         .with_ignore_errors(robj_to!(bool, ignore_errors)?)
+        .with_schema(robj_to!(Option, WrapSchema, schema)?.map(|x| pl::Arc::new(x.0)))
+        .with_cloud_options(cloud_options.clone());
+
+    // the rows of NDJSON end at every newline, as strings cannot hold one
+    let file_reader = TextReader {
+        reader: reader.clone(),
+        eol_char: b'\n',
+        quote_char: None,
+    };
+    let file_cloud_options = cloud_options.clone();
+    let hive_lf = scan_hive_partitioned(
+        &vec_pathbuf,
+        glob,
+        cloud_options.as_ref(),
+        hive_options,
+        n_rows,
+        opt_rowindex.clone(),
+        include_file_paths.clone(),
+        move |path| {
+            scan_file(
+                file_reader.clone(),
+                path,
+                file_cloud_options.as_ref(),
+                infer_lines,
+            )
+        },
+    )
+    .map_err(polars_to_rpolars_err)?;
+    if let Some(lf) = hive_lf {
+        return Ok(RPolarsLazyFrame(lf));
+    }
+    let decompressed_lf = scan_decompressed(
+        TextReader {
+            reader: reader.clone(),
+            eol_char: b'\n',
+            quote_char: None,
+        },
+        &vec_pathbuf,
+        glob,
+        cloud_options.as_ref(),
+        infer_lines,
+        n_rows,
+        opt_rowindex.clone(),
+        include_file_paths.clone(),
+    )
+    .map_err(polars_to_rpolars_err)?;
+    if let Some(lf) = decompressed_lf {
+        return Ok(RPolarsLazyFrame(lf));
+    }
+
+    // the ndjson reader always expands glob patterns, which is only a no-op for plain paths
+    let has_pattern = vec_pathbuf
+        .iter()
+        .any(|path| path.to_string_lossy().contains(['*', '?', '[']));
+    if !glob && has_pattern {
+        return rerr()
+            .plain("`glob = FALSE` is not supported for paths containing '*', '?' or '['")
+            .bad_arg("glob");
+    }
+
+    reader
+        .with_n_rows(n_rows)
+        .with_row_index(opt_rowindex)
+        .with_include_file_paths(include_file_paths)
         .finish()
         .map_err(polars_to_rpolars_err)
         .map(RPolarsLazyFrame)
 }
 
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn read_ndjson_from_raw(
+    bits: Robj,
+    infer_schema_length: Robj,
+    batch_size: Robj,
+    n_rows: Robj,
+    low_memory: Robj,
+    rechunk: Robj,
+    row_index_name: Robj,
+    row_index_offset: Robj,
+    ignore_errors: Robj,
+    schema: Robj,
+) -> RResult<RPolarsDataFrame> {
+    let bits = robj_to!(Raw, bits)?;
+    let offset = robj_to!(Option, u32, row_index_offset)?.unwrap_or(0);
+    let mut opt_rowindex = robj_to!(Option, String, row_index_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+
+    let mut reader = pl::JsonLineReader::new(std::io::Cursor::new(bits))
+        .infer_schema_len(robj_to!(Option, nonzero_usize, infer_schema_length)?)
+        .with_chunk_size(robj_to!(Option, nonzero_usize, batch_size)?)
+        .with_n_rows(robj_to!(Option, usize, n_rows)?)
+        .low_memory(robj_to!(bool, low_memory)?)
+        .with_rechunk(robj_to!(bool, rechunk)?)
+        .with_ignore_errors(robj_to!(bool, ignore_errors)?)
+        .with_row_index(opt_rowindex.as_mut());
+    if let Some(schema) = robj_to!(Option, WrapSchema, schema)? {
+        reader = reader.with_schema(pl::Arc::new(schema.0));
+    }
+    reader
+        .finish()
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsDataFrame)
+}
+
+// the dtypes are inferred from the first `infer_schema_length` lines, or all lines if NULL
+#[extendr]
+pub fn ndjson_schema(source: Robj, infer_schema_length: Robj) -> RResult<Pairlist> {
+    let infer_schema_length = robj_to!(Option, nonzero_usize, infer_schema_length)?;
+    let schema = if source.rtype() == Rtype::Raw {
+        let bits = robj_to!(Raw, source)?;
+        polars::io::ndjson::infer_schema(&mut std::io::Cursor::new(bits), infer_schema_length)
+    } else {
+        let path = robj_to!(String, source)?;
+        let file = std::fs::File::open(&path)
+            .map_err(|err| RPolarsErr::new().plain(format!("failed to open '{path}': {err}")))?;
+        polars::io::ndjson::infer_schema(&mut std::io::BufReader::new(file), infer_schema_length)
+    }
+    .map_err(polars_to_rpolars_err)?;
+    Ok(schema_to_pairlist(&schema))
+}
+
 extendr_module! {
     mod read_ndjson;
     fn new_from_ndjson;
+    fn read_ndjson_from_raw;
+    fn ndjson_schema;
 }
@@ -1,12 +1,16 @@
 use crate::lazy::dataframe::RPolarsLazyFrame;
+use crate::rdataframe::RPolarsDataFrame;
 use crate::rdatatype::robj_to_cloud_options;
+use crate::rdatatype::RPolarsDataType;
 use crate::robj_to;
 use crate::rpolarserr::{polars_to_rpolars_err, RResult};
 
 use extendr_api::Rinternals;
-use extendr_api::{extendr, extendr_module, Robj};
+use extendr_api::{extendr, extendr_module, list, List, Robj};
 use polars::io::{HiveOptions, RowIndex};
-use polars::prelude::{self as pl, Arc};
+use polars::prelude::{self as pl, Arc, IntoSeries, SchemaExt, SchemaExtPl, SerReader};
+use polars_parquet::read::statistics::{deserialize, Statistics};
+use polars_parquet::read::{infer_schema, read_metadata, FileMetadata};
 
 #[allow(clippy::too_many_arguments)]
 #[extendr]
@@ -66,7 +70,266 @@ pub fn new_from_parquet(
         .map(RPolarsLazyFrame)
 }
 
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+pub fn read_parquet_from_raw(
+    bits: Robj,
+    n_rows: Robj,
+    parallel: Robj,
+    rechunk: Robj,
+    row_name: Robj,
+    row_index: Robj,
+    use_statistics: Robj,
+    low_memory: Robj,
+    schema: Robj,
+    allow_missing_columns: Robj,
+) -> RResult<RPolarsDataFrame> {
+    let bits = robj_to!(Raw, bits)?;
+    let offset = robj_to!(Option, u32, row_index)?.unwrap_or(0);
+    let opt_row_index = robj_to!(Option, String, row_name)?.map(|name| RowIndex {
+        name: name.into(),
+        offset,
+    });
+    let allow_missing_columns = robj_to!(bool, allow_missing_columns)?;
+
+    let mut reader = pl::ParquetReader::new(std::io::Cursor::new(bits))
+        .with_slice(robj_to!(Option, usize, n_rows)?.map(|n_rows| (0, n_rows)))
+        .with_row_index(opt_row_index)
+        .read_parallel(robj_to!(ParallelStrategy, parallel)?)
+        .use_statistics(robj_to!(bool, use_statistics)?)
+        .set_low_memory(robj_to!(bool, low_memory)?)
+        .set_rechunk(robj_to!(bool, rechunk)?);
+    // like the scan, the given schema must match the dtypes of the file
+    if let Some(schema) = robj_to!(Option, WrapSchema, schema)? {
+        let arrow_schema = Arc::new(schema.0.to_arrow(pl::CompatLevel::newest()));
+        reader = reader
+            .with_arrow_schema_projection(&arrow_schema, None, allow_missing_columns)
+            .map_err(polars_to_rpolars_err)?;
+    }
+    reader
+        .finish()
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsDataFrame)
+}
+
+// only the footer of the file is read, from a local path or a raw vector
+fn read_file_metadata(source: Robj) -> RResult<FileMetadata> {
+    let metadata = if source.is_raw() {
+        let bits = robj_to!(Raw, source)?;
+        read_metadata(&mut std::io::Cursor::new(bits))
+    } else {
+        let path = robj_to!(String, source)?;
+        let mut f = std::fs::File::open(&path)
+            .map_err(|err| pl::polars_err!(ComputeError: "cannot open '{}': {}", path, err))
+            .map_err(polars_to_rpolars_err)?;
+        read_metadata(&mut f)
+    };
+    metadata.map_err(polars_to_rpolars_err)
+}
+
+#[extendr]
+pub fn parquet_schema(source: Robj) -> RResult<RPolarsDataType> {
+    let metadata = read_file_metadata(source)?;
+    let arrow_schema = infer_schema(&metadata).map_err(polars_to_rpolars_err)?;
+    let schema = pl::Schema::from_arrow_schema(&arrow_schema);
+    let fields = schema
+        .iter()
+        .map(|(name, dtype)| pl::Field::new(name.clone(), dtype.clone()))
+        .collect();
+    Ok(RPolarsDataType(pl::DataType::Struct(fields)))
+}
+
+#[extendr]
+pub fn parquet_metadata(source: Robj) -> RResult<List> {
+    let metadata = read_file_metadata(source)?;
+    // the serialized Arrow schema is an implementation detail of the writers
+    let (keys, values): (Vec<String>, Vec<Option<String>>) = metadata
+        .key_value_metadata
+        .iter()
+        .flatten()
+        .filter(|kv| kv.key != "ARROW:schema")
+        .map(|kv| (kv.key.clone(), kv.value.clone()))
+        .unzip();
+    let key_value_metadata = List::from_names_and_values(keys, values)?;
+    Ok(list!(
+        num_rows = metadata.num_rows as f64,
+        num_row_groups = metadata.row_groups.len() as i32,
+        num_columns = metadata.schema_descr.columns().len() as i32,
+        created_by = metadata.created_by.clone(),
+        version = metadata.version,
+        key_value_metadata = key_value_metadata,
+        sorting_columns = sorting_columns_info(&metadata),
+        columns = column_chunks_info(&metadata)?
+    ))
+}
+
+// the sorting columns of the first row group, as a list of the columns `column`, `descending`
+// and `nulls_last`
+fn sorting_columns_info(metadata: &FileMetadata) -> Robj {
+    let Some(sorting_columns) = metadata
+        .row_groups
+        .first()
+        .and_then(|rg| rg.sorting_columns())
+    else {
+        return NULL.into();
+    };
+    let leaves = metadata.schema_descr.columns();
+    let column: Vec<String> = sorting_columns
+        .iter()
+        .map(|x| {
+            leaves
+                .get(x.column_idx as usize)
+                .map(|leaf| {
+                    let path: Vec<&str> = leaf.path_in_schema.iter().map(|x| x.as_str()).collect();
+                    path.join(".")
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+    let descending: Vec<bool> = sorting_columns.iter().map(|x| x.descending).collect();
+    let nulls_last: Vec<bool> = sorting_columns.iter().map(|x| !x.nulls_first).collect();
+    list!(
+        column = column,
+        descending = descending,
+        nulls_last = nulls_last
+    )
+    .into()
+}
+
+// the compression codecs and encodings of the column chunks of every leaf column, named like
+// in the Parquet specification, e.g. "ZSTD" or "RLE_DICTIONARY"
+fn column_chunks_info(metadata: &FileMetadata) -> RResult<List> {
+    fn spec_name(x: impl std::fmt::Debug) -> String {
+        let mut name = String::new();
+        for (i, c) in format!("{x:?}").chars().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                name.push('_');
+            }
+            name.push(c.to_ascii_uppercase());
+        }
+        name
+    }
+    fn push_unique(values: &mut Vec<String>, value: String) {
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    let leaves = metadata.schema_descr.columns();
+    let mut names = Vec::with_capacity(leaves.len());
+    let mut infos = Vec::with_capacity(leaves.len());
+    for leaf in leaves {
+        let path = &leaf.path_in_schema;
+        let mut compression = Vec::new();
+        let mut encodings = Vec::new();
+        for rg in &metadata.row_groups {
+            let chunks = rg.columns_under_root_iter(path[0].as_str());
+            for chunk in chunks.into_iter().flatten() {
+                if chunk.descriptor().path_in_schema != *path {
+                    continue;
+                }
+                push_unique(&mut compression, spec_name(chunk.compression()));
+                for encoding in chunk.column_encoding() {
+                    push_unique(&mut encodings, spec_name(encoding));
+                }
+            }
+        }
+        let name: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+        names.push(name.join("."));
+        infos.push(list!(compression = compression, encodings = encodings));
+    }
+    Ok(List::from_names_and_values(names, infos)?)
+}
+
+#[extendr]
+pub fn parquet_statistics(source: Robj) -> RResult<RPolarsDataFrame> {
+    let metadata = read_file_metadata(source)?;
+    parquet_statistics_frame(&metadata)
+        .map_err(polars_to_rpolars_err)
+        .map(RPolarsDataFrame)
+}
+
+// One row per row group, with a struct column of the statistics of every column of the file.
+// The minimum and maximum have the dtype of the column and are only known for the columns that
+// are not nested.
+fn parquet_statistics_frame(metadata: &FileMetadata) -> pl::PolarsResult<pl::DataFrame> {
+    let arrow_schema = infer_schema(metadata)?;
+    let schema = pl::Schema::from_arrow_schema(&arrow_schema);
+    let row_groups = &metadata.row_groups;
+    let n = row_groups.len();
+
+    let mut columns: Vec<pl::Column> = vec![
+        pl::Series::new("row_group".into(), (0..n as u32).collect::<Vec<_>>()).into(),
+        pl::Series::new(
+            "num_rows".into(),
+            row_groups
+                .iter()
+                .map(|rg| rg.num_rows() as u64)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        pl::Series::new(
+            "total_byte_size".into(),
+            row_groups
+                .iter()
+                .map(|rg| rg.total_byte_size() as u64)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+        pl::Series::new(
+            "compressed_size".into(),
+            row_groups
+                .iter()
+                .map(|rg| rg.compressed_size() as u64)
+                .collect::<Vec<_>>(),
+        )
+        .into(),
+    ];
+
+    for (field, (name, dtype)) in arrow_schema.iter_values().zip(schema.iter()) {
+        let mut null_counts = Vec::with_capacity(n);
+        let mut distinct_counts = Vec::with_capacity(n);
+        let mut min = pl::Series::new_empty("min".into(), dtype);
+        let mut max = pl::Series::new_empty("max".into(), dtype);
+        for rg in row_groups {
+            let statistics = match rg.columns_under_root_iter(name) {
+                Some(mut chunks) => deserialize(field, &mut chunks)?,
+                None => None,
+            };
+            let statistics = match statistics {
+                Some(Statistics::Column(statistics)) => Some(statistics.into_arrow()?),
+                _ => None,
+            };
+            let (null_count, distinct_count, min_value, max_value) = match statistics {
+                Some(s) => (s.null_count, s.distinct_count, s.min_value, s.max_value),
+                None => (None, None, None, None),
+            };
+            let value = |array: Option<pl::ArrayRef>, name: &str| match array {
+                Some(array) => pl::Series::from_arrow(name.into(), array)?.cast(dtype),
+                None => Ok(pl::Series::full_null(name.into(), 1, dtype)),
+            };
+            null_counts.push(null_count);
+            distinct_counts.push(distinct_count);
+            min.append(&value(min_value, "min")?)?;
+            max.append(&value(max_value, "max")?)?;
+        }
+        let fields = [
+            pl::Series::new("null_count".into(), null_counts),
+            pl::Series::new("distinct_count".into(), distinct_counts),
+            min,
+            max,
+        ];
+        let stats = pl::StructChunked::from_series(name.clone(), n, fields.iter())?;
+        columns.push(stats.into_series().into());
+    }
+    pl::DataFrame::new(columns)
+}
+
 extendr_module! {
     mod read_parquet;
     fn new_from_parquet;
+    fn read_parquet_from_raw;
+    fn parquet_schema;
+    fn parquet_metadata;
+    fn parquet_statistics;
 }
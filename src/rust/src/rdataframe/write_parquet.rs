@@ -0,0 +1,401 @@
+use crate::arrow_interop::field_metadata::{self, ColumnsMetadata};
+use crate::lazy::batch_sink::{sink_batches, BatchWriter};
+use crate::rdatatype::new_parquet_compression;
+use crate::robj_to;
+use crate::rpolarserr::*;
+use extendr_api::prelude::*;
+use polars::io::parquet::write::get_encodings;
+use polars::prelude::{self as pl, CompatLevel};
+use polars_parquet::parquet::error::ParquetError;
+use polars_parquet::write::{
+    array_to_columns, to_parquet_schema, write_metadata_sidecar, Compressor, DynIter,
+    DynStreamingIterator, Encoding, FallibleStreamingIterator, FileWriter, KeyValue, ParquetType,
+    Version, WriteOptions,
+};
+use polars_parquet_format::SortingColumn;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// the row group size of the polars parquet writer
+const DEFAULT_ROW_GROUP_SIZE: usize = 512 * 512;
+
+/// The options of the Parquet writers that the native polars writers do not support.
+#[derive(Default)]
+pub struct ParquetExtraOptions {
+    key_value_metadata: Vec<KeyValue>,
+    column_compression: Vec<(String, pl::ParquetCompression)>,
+    column_encoding: Vec<(String, Encoding)>,
+    // the name of the column, whether it is sorted in descending order and with the nulls first
+    sorting_columns: Vec<(String, bool, bool)>,
+}
+
+impl ParquetExtraOptions {
+    pub fn is_empty(&self) -> bool {
+        self.key_value_metadata.is_empty()
+            && self.column_compression.is_empty()
+            && self.column_encoding.is_empty()
+            && self.sorting_columns.is_empty()
+    }
+}
+
+// the list is created by `translate_parquet_extra_options()` on the R side
+pub fn robj_to_parquet_extra_options(robj: Robj) -> RResult<ParquetExtraOptions> {
+    if robj.is_null() {
+        return Ok(ParquetExtraOptions::default());
+    }
+    let Some(list) = robj.as_list() else {
+        return rerr().plain("must be a list").bad_robj(&robj);
+    };
+    let hm = list.into_hashmap();
+    let get = |name: &str| hm.get(name).cloned().unwrap_or_else(|| NULL.into());
+
+    let key_value_metadata = named_elements(get("key_value_metadata"))
+        .into_iter()
+        .map(|(key, value)| {
+            Ok(KeyValue {
+                key,
+                value: Some(robj_to!(String, value)?),
+            })
+        })
+        .collect::<RResult<Vec<_>>>()?;
+
+    let column_compression = named_elements(get("column_compression"))
+        .into_iter()
+        .map(|(name, compression)| {
+            let Some(compression) = compression.as_list() else {
+                return rerr()
+                    .plain("must be a list of a compression method and level")
+                    .bad_robj(&compression)
+                    .bad_arg(&name);
+            };
+            let method = compression.elt(0)?;
+            let level = compression.elt(1)?;
+            Ok((name, new_parquet_compression(method, level)?))
+        })
+        .collect::<RResult<Vec<_>>>()?;
+
+    let column_encoding = named_elements(get("column_encoding"))
+        .into_iter()
+        .map(|(name, encoding)| {
+            let encoding = match robj_to!(str, encoding)? {
+                "plain" => Encoding::Plain,
+                "dictionary" => Encoding::RleDictionary,
+                "delta_binary_packed" => Encoding::DeltaBinaryPacked,
+                "delta_length_byte_array" => Encoding::DeltaLengthByteArray,
+                s => {
+                    return rerr().notachoice(format!(
+                        "encoding ('{s}') must be one of 'plain', 'dictionary', \
+                         'delta_binary_packed', 'delta_length_byte_array'"
+                    ))
+                }
+            };
+            Ok((name, encoding))
+        })
+        .collect::<RResult<Vec<_>>>()?;
+
+    // a list of the columns `column`, `descending` and `nulls_last`
+    let sorting_columns = named_elements(get("sorting_columns"))
+        .into_iter()
+        .collect::<std::collections::HashMap<_, _>>();
+    let sorting_column = |name: &str| sorting_columns.get(name).cloned().unwrap_or(NULL.into());
+    let column = sorting_column("column");
+    let descending = sorting_column("descending");
+    let nulls_last = sorting_column("nulls_last");
+    let sorting_columns = robj_to!(Vec, String, column)?
+        .into_iter()
+        .zip(robj_to!(Vec, bool, descending)?)
+        .zip(robj_to!(Vec, bool, nulls_last)?)
+        .map(|((column, descending), nulls_last)| (column, descending, !nulls_last))
+        .collect();
+
+    Ok(ParquetExtraOptions {
+        key_value_metadata,
+        column_compression,
+        column_encoding,
+        sorting_columns,
+    })
+}
+
+fn named_elements(robj: Robj) -> Vec<(String, Robj)> {
+    robj.as_list()
+        .map(|list| {
+            list.iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Writes DataFrames as the row groups of a Parquet file. Unlike the polars writer, the options
+/// of `extras` can be applied: key-value metadata, the compression and encoding of single columns
+/// and the sorting columns of the row groups. The arrow schema of the file may carry the field
+/// metadata of the columns.
+pub struct ParquetExtrasWriter<W: Write> {
+    writer: FileWriter<FooterWriter<W>>,
+    fields: Vec<ParquetType>,
+    encodings: Vec<Vec<Encoding>>,
+    column_options: Vec<WriteOptions>,
+    row_group_size: usize,
+    key_value_metadata: Option<Vec<KeyValue>>,
+    sorting_columns: Option<Vec<SortingColumn>>,
+    // the rows that do not fill a row group yet
+    pending: Option<pl::DataFrame>,
+    // set to keep the footer that is written by `writer.end()` in memory
+    buffer_footer: Arc<AtomicBool>,
+}
+
+impl<W: Write> ParquetExtrasWriter<W> {
+    /// `arrow_schema` is the arrow schema of `schema`.
+    pub fn new(
+        writer: W,
+        schema: &pl::Schema,
+        arrow_schema: pl::ArrowSchema,
+        options: &pl::ParquetWriteOptions,
+        extras: &ParquetExtraOptions,
+    ) -> pl::PolarsResult<Self> {
+        let parquet_schema = to_parquet_schema(&arrow_schema)?;
+        let file_options = WriteOptions {
+            statistics: options.statistics,
+            compression: options.compression.into(),
+            version: Version::V1,
+            data_page_size: options.data_page_size,
+        };
+        let column_index = |name: &str| {
+            schema
+                .index_of(name)
+                .ok_or_else(|| pl::polars_err!(col_not_found = name))
+        };
+
+        let mut column_options = vec![file_options; schema.len()];
+        for (name, compression) in &extras.column_compression {
+            column_options[column_index(name)?].compression = (*compression).into();
+        }
+        let mut encodings = get_encodings(&arrow_schema);
+        for (name, encoding) in &extras.column_encoding {
+            for leaf in encodings[column_index(name)?].iter_mut() {
+                *leaf = *encoding;
+            }
+        }
+        // the sorting columns refer to the leaf columns of the Parquet schema
+        let sorting_columns = extras
+            .sorting_columns
+            .iter()
+            .map(|(name, descending, nulls_first)| {
+                let index = column_index(name)?;
+                pl::polars_ensure!(
+                    encodings[index].len() == 1,
+                    InvalidOperation: "sorting column '{}' must not be nested", name
+                );
+                let leaf: usize = encodings[..index].iter().map(|leaves| leaves.len()).sum();
+                Ok(SortingColumn {
+                    column_idx: leaf as i32,
+                    descending: *descending,
+                    nulls_first: *nulls_first,
+                })
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+
+        let fields = parquet_schema.fields().to_vec();
+        let buffer_footer = Arc::new(AtomicBool::new(false));
+        let writer = FooterWriter {
+            inner: writer,
+            buffered: buffer_footer.clone(),
+            tail: Vec::new(),
+        };
+        let writer =
+            FileWriter::new_with_parquet_schema(writer, arrow_schema, parquet_schema, file_options);
+        Ok(ParquetExtrasWriter {
+            writer,
+            fields,
+            encodings,
+            column_options,
+            row_group_size: options
+                .row_group_size
+                .unwrap_or(DEFAULT_ROW_GROUP_SIZE)
+                .max(1),
+            key_value_metadata: (!extras.key_value_metadata.is_empty())
+                .then(|| extras.key_value_metadata.clone()),
+            sorting_columns: (!sorting_columns.is_empty()).then_some(sorting_columns),
+            pending: None,
+            buffer_footer,
+        })
+    }
+
+    /// Write `df` in row groups of `row_group_size` rows. The rows that do not fill a row group
+    /// are kept until the next call, or until the file is finished.
+    pub fn write(&mut self, df: &pl::DataFrame) -> pl::PolarsResult<()> {
+        let mut pending = match self.pending.take() {
+            Some(mut pending) => {
+                pending.vstack_mut(df)?;
+                pending
+            }
+            None => df.clone(),
+        };
+        while pending.height() >= self.row_group_size {
+            let (head, tail) = pending.split_at(self.row_group_size as i64);
+            self.write_row_group(head)?;
+            pending = tail;
+        }
+        if pending.height() > 0 {
+            self.pending = Some(pending);
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self, mut df: pl::DataFrame) -> pl::PolarsResult<()> {
+        df.as_single_chunk_par();
+        for batch in df.iter_chunks(CompatLevel::newest(), true) {
+            self.write_arrays(batch.into_arrays())?;
+        }
+        Ok(())
+    }
+
+    fn write_arrays(&mut self, arrays: Vec<pl::ArrayRef>) -> pl::PolarsResult<()> {
+        let pages = arrays
+            .into_iter()
+            .zip(&self.fields)
+            .zip(&self.encodings)
+            .zip(&self.column_options)
+            .map(|(((array, type_), encoding), options)| {
+                array_to_columns(array, type_.clone(), *options, encoding)
+                    .map(|columns| (columns, options.compression))
+            })
+            .collect::<pl::PolarsResult<Vec<_>>>()?;
+        let columns = pages
+            .into_iter()
+            .flat_map(|(columns, compression)| {
+                columns.into_iter().map(move |pages| {
+                    let pages = DynIter::new(
+                        pages
+                            .into_iter()
+                            .map(|page| page.map_err(ParquetError::from)),
+                    );
+                    let compressed =
+                        Compressor::new(pages, compression, vec![]).map_err(pl::PolarsError::from);
+                    pl::PolarsResult::Ok(DynStreamingIterator::new(compressed))
+                })
+            })
+            .collect::<Vec<_>>();
+        self.writer.write(DynIter::new(columns.into_iter()))
+    }
+
+    /// Write the pending rows and the footer of the file, and return the inner writer.
+    pub fn finish(mut self) -> pl::PolarsResult<W> {
+        if let Some(pending) = self.pending.take() {
+            self.write_row_group(pending)?;
+        }
+        let Some(sorting_columns) = self.sorting_columns.take() else {
+            self.writer.end(self.key_value_metadata.take())?;
+            let mut writer = self.writer.into_inner().inner;
+            writer.flush()?;
+            return Ok(writer);
+        };
+
+        // the writer of polars_parquet does not set the sorting columns of the row groups, so
+        // the footer that it writes is replaced by one with the sorting columns
+        self.buffer_footer.store(true, Ordering::Relaxed);
+        self.writer.end(self.key_value_metadata.take())?;
+        let (writer, mut metadata) = self.writer.into_inner_and_metadata();
+        let FooterWriter {
+            mut inner, tail, ..
+        } = writer;
+        // the footer is the metadata, its length in 4 bytes and the 4 magic bytes
+        let n = tail.len();
+        let metadata_len = u32::from_le_bytes([tail[n - 8], tail[n - 7], tail[n - 6], tail[n - 5]]);
+        inner.write_all(&tail[..n - 8 - metadata_len as usize])?;
+        for row_group in metadata.row_groups.iter_mut() {
+            row_group.sorting_columns = Some(sorting_columns.clone());
+        }
+        // a sidecar is the magic bytes of the header followed by the footer
+        let mut footer = Vec::new();
+        write_metadata_sidecar(&mut footer, &metadata)?;
+        inner.write_all(&footer[4..])?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write + Send + 'static> BatchWriter for ParquetExtrasWriter<W> {
+    fn write_batch(&mut self, df: pl::DataFrame) -> pl::PolarsResult<()> {
+        self.write(&df)
+    }
+}
+
+// Passes the bytes through to the inner writer until `buffered` is set, and keeps them in `tail`
+// afterwards, so that the footer of the file can be rewritten.
+struct FooterWriter<W: Write> {
+    inner: W,
+    buffered: Arc<AtomicBool>,
+    tail: Vec<u8>,
+}
+
+impl<W: Write> Write for FooterWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.buffered.load(Ordering::Relaxed) {
+            self.tail.extend_from_slice(buf);
+            Ok(buf.len())
+        } else {
+            self.inner.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffered.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            self.inner.flush()
+        }
+    }
+}
+/// Write `df` with the options of `write_parquet()` and `to_raw_parquet()`. The polars writer is
+/// used unless the extra options or the field metadata `md` of the columns must be written.
+pub fn write_frame<W: Write>(
+    writer: W,
+    df: &pl::DataFrame,
+    md: &ColumnsMetadata,
+    options: &pl::ParquetWriteOptions,
+    extras: &ParquetExtraOptions,
+) -> pl::PolarsResult<()> {
+    if !extras.is_empty() || !md.is_empty() {
+        return write_frame_with_extras(writer, df, md, options, extras);
+    }
+    pl::ParquetWriter::new(writer)
+        .with_compression(options.compression)
+        .with_statistics(options.statistics)
+        .with_row_group_size(options.row_group_size)
+        .with_data_page_size(options.data_page_size)
+        .set_parallel(true)
+        .finish(&mut df.clone())
+        .map(|_| ())
+}
+
+/// Write `df` with the field metadata `md` of its columns.
+pub fn write_frame_with_extras<W: Write>(
+    writer: W,
+    df: &pl::DataFrame,
+    md: &ColumnsMetadata,
+    options: &pl::ParquetWriteOptions,
+    extras: &ParquetExtraOptions,
+) -> pl::PolarsResult<()> {
+    let arrow_schema = field_metadata::df_arrow_schema(df, md, CompatLevel::newest());
+    let mut writer = ParquetExtrasWriter::new(writer, df.schema(), arrow_schema, options, extras)?;
+    writer.write(df)?;
+    writer.finish().map(|_| ())
+}
+
+/// The native parquet sink cannot apply `extras`, so the batches of the streaming engine are
+/// written by a `ParquetExtrasWriter` as they are produced, see `sink_batches()`.
+pub fn sink_with_extras<W: Write + Send + 'static>(
+    lf: pl::LazyFrame,
+    writer: W,
+    options: &pl::ParquetWriteOptions,
+    extras: &ParquetExtraOptions,
+) -> pl::PolarsResult<()> {
+    let schema = lf.clone().collect_schema()?;
+    let arrow_schema = schema.to_arrow(CompatLevel::newest());
+    let writer = ParquetExtrasWriter::new(writer, &schema, arrow_schema, options, extras)?;
+    sink_batches(lf, options.maintain_order, writer)?
+        .finish()
+        .map(|_| ())
+}
@@ -1,3 +1,4 @@
+use crate::arrow_interop::field_metadata::FieldMetadata;
 use crate::robj_to;
 
 use crate::utils::wrappers::Wrap;
@@ -13,25 +14,31 @@ use polars::prelude::AsofStrategy;
 use std::num::NonZeroUsize;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct RPolarsRField(pub pl::Field);
+pub struct RPolarsRField(pub pl::Field, pub FieldMetadata);
 
 #[extendr]
 impl RPolarsRField {
     fn new(name: String, datatype: &RPolarsDataType) -> RPolarsRField {
         let name = name.into();
-        RPolarsRField(pl::Field {
-            name,
-            dtype: datatype.0.clone(),
-        })
+        RPolarsRField(
+            pl::Field {
+                name,
+                dtype: datatype.0.clone(),
+            },
+            FieldMetadata::default(),
+        )
     }
 
     pub fn print(&self) {
         rprintln!("{:#?}", self.0);
+        if !self.1.is_empty() {
+            rprintln!("{:#?}", self.1);
+        }
     }
 
     #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> Self {
-        RPolarsRField(self.0.clone())
+        RPolarsRField(self.0.clone(), self.1.clone())
     }
 
     pub fn get_name(&self) -> String {
@@ -48,6 +55,31 @@ impl RPolarsRField {
     pub fn set_datatype_mut(&mut self, datatype: &RPolarsDataType) {
         self.0.dtype = datatype.0.clone()
     }
+
+    pub fn get_metadata(&self) -> Robj {
+        self.1.metadata_robj()
+    }
+
+    pub fn set_metadata_mut(&mut self, metadata: Robj) -> RResult<()> {
+        self.1.set_metadata(metadata)
+    }
+
+    pub fn get_extension_name(&self) -> Robj {
+        self.1.extension_name_robj()
+    }
+
+    pub fn get_extension_metadata(&self) -> Robj {
+        self.1.extension_metadata_robj()
+    }
+
+    pub fn set_extension_mut(&mut self, name: Robj, metadata: Robj) -> RResult<()> {
+        self.1.set_extension(name, metadata)
+    }
+
+    // the value of the `field_metadata` attribute of a Series with this field
+    pub fn get_field_metadata(&self) -> Robj {
+        self.1.to_robj()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -112,6 +144,25 @@ impl RPolarsDataType {
         )?)))
     }
 
+    pub fn new_decimal(precision: Robj, scale: Robj) -> RResult<RPolarsDataType> {
+        let precision = robj_to!(Option, usize, precision)?;
+        let scale = robj_to!(usize, scale)?;
+        match precision {
+            Some(p) if !(1..=38).contains(&p) => rerr()
+                .bad_val(p.to_string())
+                .misvalued("must be between 1 and 38")
+                .bad_arg("precision"),
+            Some(p) if scale > p => rerr()
+                .bad_val(scale.to_string())
+                .misvalued("must not be greater than the precision")
+                .bad_arg("scale"),
+            _ => Ok(RPolarsDataType(pl::DataType::Decimal(
+                precision,
+                Some(scale),
+            ))),
+        }
+    }
+
     pub fn new_list(inner: &RPolarsDataType) -> RPolarsDataType {
         RPolarsDataType(pl::DataType::List(Box::new(inner.0.clone())))
     }
@@ -205,6 +256,14 @@ impl RPolarsDataType {
             pl::DataType::List(inner) => {
                 list!(RPolarsDataType(*inner).into_robj())
             }
+            pl::DataType::Decimal(precision, scale) => list!(
+                precision = if let Some(precision) = precision {
+                    (precision as i32).into_robj()
+                } else {
+                    extendr_api::NULL.into_robj()
+                },
+                scale = scale.map(|x| x as i32).unwrap_or(0),
+            ),
             _ => list!(),
         }
     }
@@ -461,6 +520,7 @@ pub fn literal_to_any_value(litval: pl::LiteralValue) -> RResult<pl::AnyValue<'s
         lv::Int32(x) => Ok(av::Int32(x)),
         lv::Int64(x) => Ok(av::Int64(x)),
         lv::Int8(x) => Ok(av::Int8(x)),
+        lv::Decimal(x, scale) => Ok(av::Decimal(x, scale)),
         lv::Null => Ok(av::Null),
         // lv::Range {
         //     low,
@@ -570,6 +630,31 @@ pub fn new_parquet_compression(
     .misvalued("must be one of 'uncompressed', 'snappy', 'gzip', 'brotli', 'zstd'")
 }
 
+pub fn new_text_compression(
+    compression_method: Robj,
+    compression_level: Robj,
+) -> RResult<Option<crate::rdataframe::compression::TextCompression>> {
+    use crate::rdataframe::compression::TextCompression::*;
+    match robj_to_rchoice(compression_method)?.as_str() {
+        "uncompressed" => Ok(None),
+        "gzip" => match robj_to!(Option, u32, compression_level)?.unwrap_or(6) {
+            level @ 0..=9 => Ok(Some(Gzip(level))),
+            level => rerr()
+                .bad_val(level.to_string())
+                .misvalued("must be a gzip compression level between 0 and 9"),
+        },
+        "zstd" => match robj_to!(Option, i32, compression_level)?.unwrap_or(3) {
+            level @ 1..=22 => Ok(Some(Zstd(level))),
+            level => rerr()
+                .bad_val(level.to_string())
+                .misvalued("must be a zstd compression level between 1 and 22"),
+        },
+        s => rerr().notachoice(format!(
+            "compression ('{s}') must be one of 'uncompressed', 'gzip', 'zstd'"
+        )),
+    }
+}
+
 pub fn new_ipc_compression(robj: Robj) -> RResult<Option<pl::IpcCompression>> {
     match robj_to_rchoice(robj)?.as_str() {
         "uncompressed" => Ok(None),
@@ -581,6 +666,18 @@ pub fn new_ipc_compression(robj: Robj) -> RResult<Option<pl::IpcCompression>> {
     }
 }
 
+pub fn new_avro_compression(robj: Robj) -> RResult<Option<polars::io::avro::AvroCompression>> {
+    use polars::io::avro::AvroCompression;
+    match robj_to_rchoice(robj)?.as_str() {
+        "uncompressed" => Ok(None),
+        "deflate" => Ok(Some(AvroCompression::Deflate)),
+        "snappy" => Ok(Some(AvroCompression::Snappy)),
+        s => rerr().notachoice(format!(
+            "AvroCompression ('{s}') must be one of 'uncompressed', 'deflate', 'snappy'"
+        )),
+    }
+}
+
 pub fn new_rolling_cov_options(
     window_size: Robj,
     min_periods: Robj,
@@ -747,6 +844,15 @@ pub fn robj_to_wrap_schema(robj: Robj) -> RResult<Wrap<pl::Schema>> {
     Ok(schema)
 }
 
+// the named list of DataTypes returned by `<LazyFrame>$schema` and the `read_*_schema()` functions
+pub fn schema_to_pairlist(schema: &pl::Schema) -> Pairlist {
+    Pairlist::from_pairs(
+        schema
+            .iter()
+            .map(|(name, dtype)| (name.as_str(), RPolarsDataType(dtype.clone()))),
+    )
+}
+
 pub fn robj_to_compat_level(robj: Robj) -> RResult<pl::CompatLevel> {
     use pl::CompatLevel;
     let out;
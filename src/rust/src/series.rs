@@ -5,13 +5,14 @@
 /// Therefore there annoyingly exists pl::Series and Series
 use crate::apply_input;
 use crate::apply_output;
+use crate::arrow_interop::field_metadata::{self, FieldMetadata};
 use crate::conversion_r_to_s::robjname2series;
-use crate::conversion_s_to_r::pl_series_to_list;
+use crate::conversion_s_to_r::{pl_series_to_list, ConversionOptions};
 use crate::handle_type;
 use crate::lazy::dsl::RPolarsExpr;
 use crate::make_r_na_fun;
 use crate::rdataframe::RPolarsDataFrame;
-use crate::rdatatype::RPolarsDataType;
+use crate::rdatatype::{RPolarsDataType, RPolarsRField};
 use crate::robj_to;
 use crate::rpolarserr::RResult;
 use crate::utils::wrappers::null_to_opt;
@@ -116,9 +117,25 @@ impl RPolarsSeries {
         panic!("somebody panicked on purpose");
     }
 
-    pub fn to_r(&self, int64_conversion: &str) -> std::result::Result<Robj, String> {
-        pl_series_to_list(&self.0, true, int64_conversion)
-            .map_err(|err| format!("in to_r: {:?}", err))
+    pub fn to_r(
+        &self,
+        int64_conversion: &str,
+        decimal_conversion: &str,
+        duration_conversion: &str,
+        time_conversion: &str,
+        array_conversion: &str,
+        struct_conversion: &str,
+    ) -> std::result::Result<Robj, String> {
+        let options = ConversionOptions::new(
+            int64_conversion,
+            decimal_conversion,
+            duration_conversion,
+            time_conversion,
+            array_conversion,
+            struct_conversion,
+        )
+        .map_err(|err| format!("in to_r: {:?}", err))?;
+        self.to_r_with(&options)
     }
     //any mut method exposed in R suffixed _mut
     pub fn rename_mut(&mut self, name: &str) {
@@ -280,9 +297,9 @@ impl RPolarsSeries {
     }
 
     pub fn alias(&self, name: &str) -> RPolarsSeries {
-        let mut s = self.0.clone();
-        s.rename(name.into());
-        RPolarsSeries(s)
+        let mut s = RPolarsSeries(self.0.clone());
+        s.rename_mut(name);
+        s
     }
 
     pub fn all(&self) -> List {
@@ -479,11 +496,11 @@ impl RPolarsSeries {
                     .map_err(polars_to_rpolars_err)?
                     .mean_reduce()
                     .into_series("".into());
-                RPolarsSeries(s).to_r("double")
+                RPolarsSeries(s).to_r_with(&ConversionOptions::default())
             }
             DataType::Datetime(_, _) | DataType::Duration(_) | DataType::Time => {
                 let s = self.0.mean_reduce().into_series("".into());
-                RPolarsSeries(s).to_r("double")
+                RPolarsSeries(s).to_r_with(&ConversionOptions::default())
             }
             _ => Ok(self.0.mean().into()),
         }
@@ -499,7 +516,7 @@ impl RPolarsSeries {
                     .median_reduce()
                     .map_err(polars_to_rpolars_err)?
                     .into_series("".into());
-                RPolarsSeries(s).to_r("double")
+                RPolarsSeries(s).to_r_with(&ConversionOptions::default())
             }
             DataType::Datetime(_, _) | DataType::Duration(_) | DataType::Time => {
                 let s = self
@@ -507,40 +524,49 @@ impl RPolarsSeries {
                     .median_reduce()
                     .map_err(polars_to_rpolars_err)?
                     .into_series("".into());
-                RPolarsSeries(s).to_r("double")
+                RPolarsSeries(s).to_r_with(&ConversionOptions::default())
             }
             _ => Ok(self.0.median().into()),
         }
     }
 
-    pub fn min(&self) -> Result<Robj, String> {
+    pub fn min(&self, decimal_conversion: &str) -> Result<Robj, String> {
         RPolarsSeries(
             self.0
                 .min_reduce()
                 .map_err(polars_to_rpolars_err)?
                 .into_series("".into()),
         )
-        .to_r("double")
+        .to_r_with(&ConversionOptions {
+            decimal_conversion,
+            ..Default::default()
+        })
     }
 
-    pub fn max(&self) -> Result<Robj, String> {
+    pub fn max(&self, decimal_conversion: &str) -> Result<Robj, String> {
         RPolarsSeries(
             self.0
                 .max_reduce()
                 .map_err(polars_to_rpolars_err)?
                 .into_series("".into()),
         )
-        .to_r("double")
+        .to_r_with(&ConversionOptions {
+            decimal_conversion,
+            ..Default::default()
+        })
     }
 
-    pub fn sum(&self) -> Result<Robj, String> {
+    pub fn sum(&self, decimal_conversion: &str) -> Result<Robj, String> {
         RPolarsSeries(
             self.0
                 .sum_reduce()
                 .map_err(polars_to_rpolars_err)?
                 .into_series("".into()),
         )
-        .to_r("double")
+        .to_r_with(&ConversionOptions {
+            decimal_conversion,
+            ..Default::default()
+        })
     }
 
     pub fn std(&self, ddof: Robj) -> Result<Robj, String> {
@@ -552,7 +578,7 @@ impl RPolarsSeries {
                 .map_err(polars_to_rpolars_err)?
                 .into_series("".into()),
         )
-        .to_r("double")
+        .to_r_with(&ConversionOptions::default())
     }
 
     pub fn var(&self, ddof: Robj) -> Result<Robj, String> {
@@ -564,7 +590,7 @@ impl RPolarsSeries {
                 .map_err(polars_to_rpolars_err)?
                 .into_series("".into()),
         )
-        .to_r("double")
+        .to_r_with(&ConversionOptions::default())
     }
 
     pub fn print(&self) {
@@ -594,9 +620,15 @@ impl RPolarsSeries {
             .collect())
     }
 
-    pub fn export_stream(&self, stream_ptr: &str, compat_level: Robj) {
-        let compat_level = robj_to!(CompatLevel, compat_level).unwrap();
-        let field = self.0.field().to_arrow(compat_level);
+    pub fn export_stream(
+        &self,
+        stream_ptr: &str,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = FieldMetadata::from_robj(field_metadata)?;
+        let field = field_metadata::series_arrow_field(&self.0, &md, compat_level);
 
         let iter_boxed = Box::new(OwnedSeriesIterator::new(self.0.clone(), compat_level));
         let mut stream = arrow::ffi::export_iterator(iter_boxed, field);
@@ -609,9 +641,10 @@ impl RPolarsSeries {
                 1,
             );
         }
+        Ok(())
     }
 
-    pub fn import_stream(stream_ptr: Robj) -> RResult<Self> {
+    pub fn import_stream(stream_ptr: Robj) -> RResult<Robj> {
         let stream_in_ptr_addr = robj_to!(usize, stream_ptr)?;
         let stream_in_ptr =
             unsafe { Box::from_raw(stream_in_ptr_addr as *mut arrow::ffi::ArrowArrayStream) };
@@ -623,14 +656,52 @@ impl RPolarsSeries {
         }
 
         let chunks = arrays.into_iter().collect::<Vec<_>>();
-        let s = pl::Series::try_from((stream.field(), chunks)).map_err(polars_to_rpolars_err)?;
-
-        Ok(s.into())
+        let s = field_metadata::series_from_arrow(stream.field(), chunks)
+            .map_err(polars_to_rpolars_err)?;
+        let md = FieldMetadata::from_arrow_field(stream.field());
+        field_metadata::set_attr(RPolarsSeries(s).into(), md.to_robj())
     }
 
-    pub fn from_arrow_array_robj(name: Robj, array: Robj) -> Result<Self, String> {
+    pub fn export_array(
+        &self,
+        array_ptr: Robj,
+        schema_ptr: Robj,
+        compat_level: Robj,
+        field_metadata: Robj,
+    ) -> RResult<()> {
+        use crate::arrow_interop::{export_array_to_c, robj_to_ptr_addr};
+        let compat_level = robj_to!(CompatLevel, compat_level)?;
+        let md = FieldMetadata::from_robj(field_metadata)?;
+        let array_addr = robj_to_ptr_addr(array_ptr)?;
+        let schema_addr = robj_to_ptr_addr(schema_ptr)?;
+        let s = self.0.rechunk();
+        let field = field_metadata::series_arrow_field(&self.0, &md, compat_level);
+        unsafe { export_array_to_c(s.to_arrow(0, compat_level), &field, array_addr, schema_addr) };
+        Ok(())
+    }
+
+    pub fn import_array(array_ptr: Robj, schema_ptr: Robj) -> RResult<Robj> {
+        use crate::arrow_interop::{import_array_from_c, robj_to_ptr_addr};
+        let array_addr = robj_to_ptr_addr(array_ptr)?;
+        let schema_addr = robj_to_ptr_addr(schema_ptr)?;
+        let (field, array) = unsafe { import_array_from_c(array_addr, schema_addr) }
+            .map_err(polars_to_rpolars_err)?;
+        let s = field_metadata::series_from_arrow(&field, vec![array])
+            .map_err(polars_to_rpolars_err)?;
+        let md = FieldMetadata::from_arrow_field(&field);
+        field_metadata::set_attr(RPolarsSeries(s).into(), md.to_robj())
+    }
+
+    // `field_metadata` is the `field_metadata` attribute of the Series on the R side
+    pub fn field(&self, field_metadata: Robj) -> RResult<RPolarsRField> {
+        let md = FieldMetadata::from_robj(field_metadata)?;
+        Ok(RPolarsRField(self.0.field().into_owned(), md))
+    }
+
+    pub fn from_arrow_array_robj(name: Robj, array: Robj) -> Result<Robj, String> {
         let name: PlSmallStr = robj_to!(str, name)?.into();
-        let arr = crate::arrow_interop::to_rust::arrow_array_to_rust(array)?;
+        let (field, arr) = crate::arrow_interop::to_rust::arrow_array_to_rust(array)?;
+        let md = FieldMetadata::from_arrow_field(&field).to_robj();
 
         match arr.dtype() {
             ArrowDataType::LargeList(_) => {
@@ -649,12 +720,16 @@ impl RPolarsSeries {
                 if fast_explode {
                     out.set_fast_explode()
                 }
-                Ok(out.into_series().into())
+                Ok(field_metadata::set_attr(
+                    RPolarsSeries(out.into_series()).into(),
+                    md,
+                )?)
             }
             _ => {
-                let series_res: Result<pl::Series, pl::PolarsError> =
-                    std::convert::TryFrom::try_from((name, arr));
-                Ok(series_res.map_err(|err| err.to_string())?.into())
+                let field = pl::ArrowField { name, ..field };
+                let s = field_metadata::series_from_arrow(&field, vec![arr])
+                    .map_err(|err| err.to_string())?;
+                Ok(field_metadata::set_attr(RPolarsSeries(s).into(), md)?)
             }
         }
     }
@@ -706,6 +781,10 @@ impl RPolarsSeries {
         Ok(RPolarsSeries(s))
     }
 
+    pub fn to_r_with(&self, options: &ConversionOptions) -> std::result::Result<Robj, String> {
+        pl_series_to_list(&self.0, true, options).map_err(|err| format!("in to_r: {:?}", err))
+    }
+
     pub unsafe fn into_frame(&self) -> RPolarsDataFrame {
         RPolarsDataFrame(self.0.clone().into_frame().clone())
     }
@@ -1092,6 +1092,10 @@ macro_rules! robj_to_inner {
         $crate::rdatatype::robj_to_statistics_options($a)
     };
 
+    (ParquetExtraOptions, $a:ident) => {
+        $crate::rdataframe::write_parquet::robj_to_parquet_extra_options($a)
+    };
+
     (WrapSchema, $a:ident) => {
         $crate::rdatatype::robj_to_wrap_schema($a)
     };